@@ -0,0 +1,571 @@
+//! Realis events in the NEP-297 format.
+//!
+//! Changes of the contract admins, the lockups and the staking, emitted as
+//! `EVENT_JSON:{"standard":"realis","version":..,"event":..,"data":[..]}`.
+//! Every event has its own version of the `data` schema,
+//! which must be bumped on any change of the event fields.
+use near_sdk::{
+    env,
+    json_types::{U128, U64},
+    serde::Serialize,
+    serde_json::json,
+    AccountId,
+};
+
+pub const EVENT_STANDARD: &str = "realis";
+
+pub trait RealisEvent: Serialize {
+    /// Name of the event
+    const EVENT: &'static str;
+    /// Version of the event data schema
+    const VERSION: &'static str;
+
+    fn emit(&self) {
+        env::log_str(&format!(
+            "EVENT_JSON:{}",
+            json!({
+                "standard": EVENT_STANDARD,
+                "version": Self::VERSION,
+                "event": Self::EVENT,
+                "data": [self],
+            })
+        ));
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BackendAdd<'a> {
+    pub backend_ids: &'a [AccountId],
+}
+
+impl RealisEvent for BackendAdd<'_> {
+    const EVENT: &'static str = "backend_add";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BackendRemove<'a> {
+    pub backend_ids: &'a [AccountId],
+}
+
+impl RealisEvent for BackendRemove<'_> {
+    const EVENT: &'static str = "backend_remove";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DepositWhitelistExtend<'a> {
+    pub account_ids: &'a [AccountId],
+}
+
+impl RealisEvent for DepositWhitelistExtend<'_> {
+    const EVENT: &'static str = "deposit_whitelist_extend";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DepositWhitelistReduce<'a> {
+    pub account_ids: &'a [AccountId],
+}
+
+impl RealisEvent for DepositWhitelistReduce<'_> {
+    const EVENT: &'static str = "deposit_whitelist_reduce";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MintAccountsAdd<'a> {
+    pub account_ids: &'a [AccountId],
+}
+
+impl RealisEvent for MintAccountsAdd<'_> {
+    const EVENT: &'static str = "mint_accounts_add";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MintAccountsRemove<'a> {
+    pub account_ids: &'a [AccountId],
+}
+
+impl RealisEvent for MintAccountsRemove<'_> {
+    const EVENT: &'static str = "mint_accounts_remove";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OwnerChange<'a> {
+    pub old_owner_id: &'a AccountId,
+    pub new_owner_id: &'a AccountId,
+}
+
+impl RealisEvent for OwnerChange<'_> {
+    const EVENT: &'static str = "owner_change";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LockupCreate<'a> {
+    pub index: u32,
+    pub account_id: &'a AccountId,
+    pub amount: U128,
+    pub unlock_on: U64,
+    /// Since `1.1.0`
+    pub creator: &'a AccountId,
+    /// Since `1.1.0`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<&'a str>,
+}
+
+impl RealisEvent for LockupCreate<'_> {
+    const EVENT: &'static str = "lockup_create";
+    const VERSION: &'static str = "1.1.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LockupClaim<'a> {
+    pub index: u32,
+    pub account_id: &'a AccountId,
+    /// Tokens transferred out of the lockup
+    pub amount: U128,
+}
+
+impl RealisEvent for LockupClaim<'_> {
+    const EVENT: &'static str = "lockup_claim";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LockupRevoke<'a> {
+    pub index: u32,
+    pub account_id: &'a AccountId,
+    pub refund_account_id: &'a AccountId,
+    /// Unvested tokens returned to the refund account
+    pub amount: U128,
+}
+
+impl RealisEvent for LockupRevoke<'_> {
+    const EVENT: &'static str = "lockup_revoke";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LockupRevokeRollback<'a> {
+    pub index: u32,
+    pub account_id: &'a AccountId,
+    /// Tokens the refund transfer failed to return
+    pub amount: U128,
+}
+
+impl RealisEvent for LockupRevokeRollback<'_> {
+    const EVENT: &'static str = "lockup_revoke_rollback";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LockupTransfer<'a> {
+    pub index: u32,
+    pub old_account_id: &'a AccountId,
+    pub new_account_id: &'a AccountId,
+}
+
+impl RealisEvent for LockupTransfer<'_> {
+    const EVENT: &'static str = "lockup_transfer";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LockupClaimEarly<'a> {
+    pub index: u32,
+    pub account_id: &'a AccountId,
+    /// Tokens received by the beneficiary
+    pub amount: U128,
+    pub penalty: U128,
+    pub penalty_receiver_id: &'a AccountId,
+}
+
+impl RealisEvent for LockupClaimEarly<'_> {
+    const EVENT: &'static str = "lockup_claim_early";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OperatorGrant<'a> {
+    pub owner_id: &'a AccountId,
+    pub operator_id: &'a AccountId,
+    /// Xtokens the operator can unstake or restake, unlimited if `None`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowance: Option<U128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<U64>,
+}
+
+impl RealisEvent for OperatorGrant<'_> {
+    const EVENT: &'static str = "operator_grant";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OperatorRevoke<'a> {
+    pub owner_id: &'a AccountId,
+    pub operator_id: &'a AccountId,
+}
+
+impl RealisEvent for OperatorRevoke<'_> {
+    const EVENT: &'static str = "operator_revoke";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct UnstakeRollback<'a> {
+    pub account_id: &'a AccountId,
+    /// Tokens refunded by the lockup
+    pub amount: U128,
+    /// Xtokens restored to the account
+    pub xtoken_amount: U128,
+}
+
+impl RealisEvent for UnstakeRollback<'_> {
+    const EVENT: &'static str = "unstake_rollback";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EmergencyMode {
+    pub enabled: bool,
+}
+
+impl RealisEvent for EmergencyMode {
+    const EVENT: &'static str = "emergency_mode";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EmergencyWithdraw<'a> {
+    pub account_id: &'a AccountId,
+    pub amount: U128,
+    pub xtoken_amount: U128,
+}
+
+impl RealisEvent for EmergencyWithdraw<'_> {
+    const EVENT: &'static str = "emergency_withdraw";
+    const VERSION: &'static str = "1.0.0";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::get_logs;
+
+    fn alice() -> AccountId {
+        AccountId::new_unchecked("alice.near".to_owned())
+    }
+
+    fn bob() -> AccountId {
+        AccountId::new_unchecked("bob.near".to_owned())
+    }
+
+    #[test]
+    fn backend_add() {
+        BackendAdd {
+            backend_ids: &[alice(), bob()],
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"backend_ids":["alice.near","bob.near"]}],"event":"backend_add","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn backend_remove() {
+        BackendRemove {
+            backend_ids: &[alice()],
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"backend_ids":["alice.near"]}],"event":"backend_remove","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn deposit_whitelist_extend() {
+        DepositWhitelistExtend {
+            account_ids: &[alice()],
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"account_ids":["alice.near"]}],"event":"deposit_whitelist_extend","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn deposit_whitelist_reduce() {
+        DepositWhitelistReduce {
+            account_ids: &[alice(), bob()],
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"account_ids":["alice.near","bob.near"]}],"event":"deposit_whitelist_reduce","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn mint_accounts_add() {
+        MintAccountsAdd {
+            account_ids: &[bob()],
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"account_ids":["bob.near"]}],"event":"mint_accounts_add","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn mint_accounts_remove() {
+        MintAccountsRemove {
+            account_ids: &[bob()],
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"account_ids":["bob.near"]}],"event":"mint_accounts_remove","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn owner_change() {
+        OwnerChange {
+            old_owner_id: &alice(),
+            new_owner_id: &bob(),
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"new_owner_id":"bob.near","old_owner_id":"alice.near"}],"event":"owner_change","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn lockup_create() {
+        LockupCreate {
+            index: 7,
+            account_id: &alice(),
+            amount: U128(1_000),
+            unlock_on: U64(42),
+            creator: &bob(),
+            memo: None,
+        }
+        .emit();
+        LockupCreate {
+            index: 8,
+            account_id: &alice(),
+            amount: U128(1_000),
+            unlock_on: U64(42),
+            creator: &bob(),
+            memo: Some("Team allocation"),
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"account_id":"alice.near","amount":"1000","creator":"bob.near","index":7,"unlock_on":"42"}],"event":"lockup_create","standard":"realis","version":"1.1.0"}"#,
+                r#"EVENT_JSON:{"data":[{"account_id":"alice.near","amount":"1000","creator":"bob.near","index":8,"memo":"Team allocation","unlock_on":"42"}],"event":"lockup_create","standard":"realis","version":"1.1.0"}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn lockup_claim() {
+        LockupClaim {
+            index: 7,
+            account_id: &alice(),
+            amount: U128(1_000),
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"account_id":"alice.near","amount":"1000","index":7}],"event":"lockup_claim","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn lockup_revoke() {
+        LockupRevoke {
+            index: 7,
+            account_id: &alice(),
+            refund_account_id: &bob(),
+            amount: U128(1_000),
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"account_id":"alice.near","amount":"1000","index":7,"refund_account_id":"bob.near"}],"event":"lockup_revoke","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn lockup_revoke_rollback() {
+        LockupRevokeRollback {
+            index: 7,
+            account_id: &alice(),
+            amount: U128(1_000),
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"account_id":"alice.near","amount":"1000","index":7}],"event":"lockup_revoke_rollback","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn lockup_transfer() {
+        LockupTransfer {
+            index: 7,
+            old_account_id: &alice(),
+            new_account_id: &bob(),
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"index":7,"new_account_id":"bob.near","old_account_id":"alice.near"}],"event":"lockup_transfer","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn lockup_claim_early() {
+        LockupClaimEarly {
+            index: 7,
+            account_id: &alice(),
+            amount: U128(900),
+            penalty: U128(100),
+            penalty_receiver_id: &bob(),
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"account_id":"alice.near","amount":"900","index":7,"penalty":"100","penalty_receiver_id":"bob.near"}],"event":"lockup_claim_early","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn operator_grant() {
+        OperatorGrant {
+            owner_id: &alice(),
+            operator_id: &bob(),
+            allowance: Some(U128(100)),
+            expires_at: None,
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"allowance":"100","operator_id":"bob.near","owner_id":"alice.near"}],"event":"operator_grant","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn operator_revoke() {
+        OperatorRevoke {
+            owner_id: &alice(),
+            operator_id: &bob(),
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"operator_id":"bob.near","owner_id":"alice.near"}],"event":"operator_revoke","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn unstake_rollback() {
+        UnstakeRollback {
+            account_id: &alice(),
+            amount: U128(100),
+            xtoken_amount: U128(90),
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"account_id":"alice.near","amount":"100","xtoken_amount":"90"}],"event":"unstake_rollback","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn emergency_mode() {
+        EmergencyMode { enabled: true }.emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"enabled":true}],"event":"emergency_mode","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn emergency_withdraw() {
+        EmergencyWithdraw {
+            account_id: &alice(),
+            amount: U128(100),
+            xtoken_amount: U128(90),
+        }
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"account_id":"alice.near","amount":"100","xtoken_amount":"90"}],"event":"emergency_withdraw","standard":"realis","version":"1.0.0"}"#
+            ]
+        );
+    }
+}
@@ -0,0 +1,4 @@
+//! Code shared by the Realis contracts.
+pub mod events;
+pub mod state_version;
+pub mod upgrade;
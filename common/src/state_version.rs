@@ -0,0 +1,58 @@
+//! Version of the contract state layout.
+//!
+//! The version is stored under its own storage key, so it can be read
+//! before the state is deserialized. A missing version stands for the version `0`,
+//! the layout of the contracts deployed before the versioning.
+//!
+//! Migration of the state in `update()` goes like:
+//! ```ignore
+//! let contract = match migration_start(STATE_VERSION) {
+//!     0 => read_state::<ContractV0>().into(),
+//!     1 => read_state::<Contract>(),
+//!     _ => env::panic_str("Unknown state version"),
+//! };
+//! set_state_version(STATE_VERSION);
+//! ```
+//! so each stored version is converted to the current layout.
+use near_sdk::{
+    borsh::{BorshDeserialize, BorshSerialize},
+    env, require,
+};
+
+pub type StateVersion = u32;
+
+pub const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+
+pub fn state_version() -> StateVersion {
+    env::storage_read(STATE_VERSION_KEY)
+        .map(|value| {
+            StateVersion::try_from_slice(&value)
+                .unwrap_or_else(|_| env::panic_str("Invalid state version"))
+        })
+        .unwrap_or_default()
+}
+
+pub fn set_state_version(version: StateVersion) {
+    env::storage_write(
+        STATE_VERSION_KEY,
+        &version
+            .try_to_vec()
+            .unwrap_or_else(|_| env::panic_str("Invalid state version")),
+    );
+}
+
+/// Stored state version to migrate from to `current`.
+/// Panics if the stored state is newer than the code.
+pub fn migration_start(current: StateVersion) -> StateVersion {
+    let version = state_version();
+    require!(
+        version <= current,
+        format!("Can't downgrade the state version from {version} to {current}")
+    );
+    version
+}
+
+/// Read the stored contract state as `T`.
+pub fn read_state<T: BorshDeserialize>() -> T {
+    env::state_read().unwrap_or_else(|| env::panic_str("Not initialized"))
+}
@@ -0,0 +1,179 @@
+//! Self-upgrade of a contract with staged code.
+//!
+//! The owner stages the new wasm passing it as the raw input of `stage_code`,
+//! anyone can compare the sha256 of the staged code with a local build,
+//! and after `UPGRADE_DELAY` the owner deploys it. The deploy and the call of
+//! `update` go in the same promise batch, so a failed migration reverts the code.
+//! The staged code is removed by the callback of the batch only if it succeeded.
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    env, is_promise_success,
+    json_types::U64,
+    require,
+    serde::{Deserialize, Serialize},
+    serde_json, CryptoHash, Gas, Promise, Timestamp,
+};
+
+pub const STAGED_CODE_KEY: &[u8] = b"STAGED_CODE";
+pub const STAGED_CODE_INFO_KEY: &[u8] = b"STAGED_CODE_INFO";
+
+/// Time between staging of the code and its deploy
+pub const UPGRADE_DELAY: Timestamp = 24 * 60 * 60 * 1_000_000_000;
+pub const GAS_FOR_UPDATE: Gas = Gas(100_000_000_000_000);
+pub const GAS_FOR_DEPLOY_CALLBACK: Gas = Gas(10_000_000_000_000);
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StagedCode {
+    pub hash: CryptoHash,
+    pub staged_at: Timestamp,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StagedCodeView {
+    /// Hex encoded sha256 of the code
+    pub hash: String,
+    pub staged_at: U64,
+    pub deployable_at: U64,
+}
+
+impl From<StagedCode> for StagedCodeView {
+    fn from(staged: StagedCode) -> Self {
+        Self {
+            hash: hex_hash(&staged.hash),
+            staged_at: staged.staged_at.into(),
+            deployable_at: (staged.staged_at + UPGRADE_DELAY).into(),
+        }
+    }
+}
+
+/// Stage the raw input of the call as the new code.
+/// Replaces the previously staged code and restarts the delay.
+pub fn stage_code() -> StagedCodeView {
+    let code = env::input().unwrap_or_else(|| env::panic_str("Expected the code as input"));
+    require!(!code.is_empty(), "The code should not be empty");
+    let staged = StagedCode {
+        hash: env::sha256(&code)
+            .try_into()
+            .unwrap_or_else(|_| env::panic_str("Invalid code hash")),
+        staged_at: env::block_timestamp(),
+    };
+    env::storage_write(STAGED_CODE_KEY, &code);
+    env::storage_write(
+        STAGED_CODE_INFO_KEY,
+        &staged
+            .try_to_vec()
+            .unwrap_or_else(|_| env::panic_str("Invalid staged code")),
+    );
+    staged.into()
+}
+
+pub fn staged_code() -> Option<StagedCodeView> {
+    read_staged_code().map(Into::into)
+}
+
+pub fn remove_staged_code() {
+    env::storage_remove(STAGED_CODE_KEY);
+    env::storage_remove(STAGED_CODE_INFO_KEY);
+}
+
+/// Deploy the staged code and migrate the state with `update`.
+pub fn deploy_staged_code() -> Promise {
+    let staged = read_staged_code().unwrap_or_else(|| env::panic_str("No staged code"));
+    require!(
+        env::block_timestamp() >= staged.staged_at + UPGRADE_DELAY,
+        "The staged code can't be deployed yet"
+    );
+    let code =
+        env::storage_read(STAGED_CODE_KEY).unwrap_or_else(|| env::panic_str("No staged code"));
+
+    Promise::new(env::current_account_id())
+        .deploy_contract(code)
+        .function_call("update".to_owned(), vec![], 0, GAS_FOR_UPDATE)
+        .then(
+            Promise::new(env::current_account_id()).function_call(
+                "on_staged_code_deployed".to_owned(),
+                serde_json::json!({ "hash": hex_hash(&staged.hash) })
+                    .to_string()
+                    .into_bytes(),
+                0,
+                GAS_FOR_DEPLOY_CALLBACK,
+            ),
+        )
+}
+
+/// Remove the staged code with `hash` once it is deployed.
+/// The code staged again in between or the code of a failed deploy is kept.
+pub fn on_staged_code_deployed(hash: String) {
+    if is_promise_success() && staged_code().map(|staged| staged.hash) == Some(hash) {
+        remove_staged_code();
+    }
+}
+
+fn hex_hash(hash: &CryptoHash) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn read_staged_code() -> Option<StagedCode> {
+    env::storage_read(STAGED_CODE_INFO_KEY).map(|value| {
+        StagedCode::try_from_slice(&value).unwrap_or_else(|_| env::panic_str("Invalid staged code"))
+    })
+}
+
+/// Implement `stage_code`, `remove_staged_code`, `deploy_staged_code`,
+/// its callback and `get_staged_code` for the contract.
+/// The contract must implement `near_contract_standards::upgrade::Ownable`
+/// and the `update` method that migrates its state.
+#[macro_export]
+macro_rules! impl_staged_upgrade {
+    ($contract: ident) => {
+        #[near_sdk::near_bindgen]
+        impl $contract {
+            /// Stage the new code of the contract passed as the raw input of the call.
+            /// It can be deployed with `deploy_staged_code` after the upgrade delay.
+            ///
+            /// Requirements
+            /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+            /// * Caller must be the owner.
+            #[payable]
+            pub fn stage_code(&mut self) -> $crate::upgrade::StagedCodeView {
+                near_sdk::assert_one_yocto();
+                near_contract_standards::upgrade::Ownable::assert_owner(self);
+                $crate::upgrade::stage_code()
+            }
+
+            /// Requirements
+            /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+            /// * Caller must be the owner.
+            #[payable]
+            pub fn remove_staged_code(&mut self) {
+                near_sdk::assert_one_yocto();
+                near_contract_standards::upgrade::Ownable::assert_owner(self);
+                $crate::upgrade::remove_staged_code();
+            }
+
+            /// Deploy the staged code and migrate the state with `update` in the same batch.
+            ///
+            /// Requirements
+            /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+            /// * Caller must be the owner.
+            /// * The upgrade delay since the staging must pass.
+            #[payable]
+            pub fn deploy_staged_code(&mut self) -> near_sdk::Promise {
+                near_sdk::assert_one_yocto();
+                near_contract_standards::upgrade::Ownable::assert_owner(self);
+                $crate::upgrade::deploy_staged_code()
+            }
+
+            #[private]
+            pub fn on_staged_code_deployed(hash: String) {
+                $crate::upgrade::on_staged_code_deployed(hash);
+            }
+
+            /// Staged code with its sha256 hash, if any
+            pub fn get_staged_code(&self) -> Option<$crate::upgrade::StagedCodeView> {
+                $crate::upgrade::staged_code()
+            }
+        }
+    };
+}
@@ -1,20 +1,35 @@
-use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_contract_standards::fungible_token::{core::ext_ft_core, receiver::FungibleTokenReceiver};
 use near_sdk::{
     borsh::{self, BorshDeserialize, BorshSerialize},
     env,
     json_types::U128,
-    near_bindgen, AccountId, PanicOnDefault, PromiseOrValue,
+    near_bindgen, AccountId, Balance, Gas, PanicOnDefault, PromiseOrValue, ONE_YOCTO,
 };
 
+const GAS_FOR_ADD_TO_POOL: Gas = Gas(50_000_000_000_000);
+const GAS_FOR_REFUND_CALLBACK: Gas = Gas(5_000_000_000_000);
+
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
-pub struct FakeLockupContract {}
+pub struct FakeLockupContract {
+    /// Own tokens added to the pool of the sender before the refund
+    add_to_pool: Balance,
+}
 
 #[near_bindgen]
 impl FakeLockupContract {
     #[init]
     pub fn new() -> Self {
-        FakeLockupContract {}
+        FakeLockupContract { add_to_pool: 0 }
+    }
+
+    pub fn set_add_to_pool(&mut self, amount: U128) {
+        self.add_to_pool = amount.0;
+    }
+
+    #[private]
+    pub fn refund_callback(&mut self, unused: U128) -> U128 {
+        unused
     }
 }
 
@@ -22,11 +37,31 @@ impl FakeLockupContract {
 impl FungibleTokenReceiver for FakeLockupContract {
     fn ft_on_transfer(
         &mut self,
-        _sender_id: AccountId,
+        sender_id: AccountId,
         amount: U128,
         _msg: String,
     ) -> PromiseOrValue<U128> {
         env::log_str("Successfully transfered half of your amonut");
-        PromiseOrValue::Value(U128(amount.0 / 2))
+        let unused = U128(amount.0 / 2);
+        if self.add_to_pool == 0 {
+            return PromiseOrValue::Value(unused);
+        }
+
+        // Change the xtoken cost of the sender before the refund
+        ext_ft_core::ext(env::predecessor_account_id())
+            .with_static_gas(GAS_FOR_ADD_TO_POOL)
+            .with_attached_deposit(ONE_YOCTO)
+            .ft_transfer_call(
+                sender_id,
+                self.add_to_pool.into(),
+                None,
+                "\"AddToPool\"".to_owned(),
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_REFUND_CALLBACK)
+                    .refund_callback(unused),
+            )
+            .into()
     }
 }
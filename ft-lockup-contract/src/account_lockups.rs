@@ -0,0 +1,134 @@
+//! Lockup indexes of accounts.
+//!
+//! Each account has its own `TreeMap` of the lockup indexes, so adding or removing
+//! a lockup costs `O(log n)` of the account lockups and the indexes are read in order.
+//! Sets of the legacy `HashSet` layout are moved to the new layout
+//! on the first change of the account lockups.
+use crate::{storage_impl::LockupStorage, *};
+
+impl Contract {
+    pub fn account_has_lockup(&self, account_id: &AccountId, index: LockupIndex) -> bool {
+        match self.account_lockups.get(account_id) {
+            Some(lockups) => lockups.contains_key(&index),
+            None => self
+                .legacy_account_lockups
+                .get(account_id)
+                .map(|lockups| lockups.contains(&index))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Lockup indexes of `account_id` greater than `after` in ascending order, all of them if `None`.
+    /// Indexes are read one by one, so the iteration can stop early.
+    pub fn get_account_lockup_indexes(
+        &self,
+        account_id: &AccountId,
+        after: Option<LockupIndex>,
+    ) -> Box<dyn Iterator<Item = LockupIndex>> {
+        match self.account_lockups.get(account_id) {
+            Some(lockups) => {
+                let first = match after {
+                    Some(after) => lockups.higher(&after),
+                    None => lockups.min(),
+                };
+                Box::new(std::iter::successors(first, move |index| {
+                    lockups.higher(index)
+                }))
+            }
+            None => {
+                let mut indexes: Vec<LockupIndex> = self
+                    .legacy_account_lockups
+                    .get(account_id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|index| after.map(|after| *index > after).unwrap_or(true))
+                    .collect();
+                indexes.sort_unstable();
+                Box::new(indexes.into_iter())
+            }
+        }
+    }
+
+    pub fn get_account_num_lockups_internal(&self, account_id: &AccountId) -> u64 {
+        match self.account_lockups.get(account_id) {
+            Some(lockups) => lockups.len(),
+            None => self
+                .legacy_account_lockups
+                .get(account_id)
+                .map(|lockups| lockups.len() as u64)
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn add_account_lockup_internal(&mut self, account_id: &AccountId, index: LockupIndex) {
+        let mut lockups = self.migrate_account_lockups_internal(account_id);
+        lockups.insert(&index, &());
+        self.account_lockups.insert(account_id, &lockups);
+    }
+
+    /// Create the lockup set of `account_id` if it has none, charging `payer_id` for it.
+    /// The storage is returned to the payer when the set is removed.
+    /// A legacy set is moved at the expense of the contract, which paid for it.
+    pub fn create_account_lockups_internal(
+        &mut self,
+        account_id: &AccountId,
+        payer_id: &AccountId,
+    ) {
+        if self.account_lockups.contains_key(account_id) {
+            return;
+        }
+        if self.legacy_account_lockups.contains_key(account_id) {
+            let lockups = self.migrate_account_lockups_internal(account_id);
+            self.account_lockups.insert(account_id, &lockups);
+            return;
+        }
+        let initial_storage_usage = env::storage_usage();
+        let lockups = self.migrate_account_lockups_internal(account_id);
+        self.account_lockups.insert(account_id, &lockups);
+        let mut storage = LockupStorage {
+            payer_id: payer_id.clone(),
+            bytes: 0,
+        };
+        self.account_lockups_storage.insert(account_id, &storage);
+        storage.bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+        self.charge_storage_internal(payer_id, storage.bytes);
+        self.account_lockups_storage.insert(account_id, &storage);
+    }
+
+    /// Returns `false` if the account has no such lockup.
+    pub fn remove_account_lockup_internal(
+        &mut self,
+        account_id: &AccountId,
+        index: LockupIndex,
+    ) -> bool {
+        let mut lockups = self.migrate_account_lockups_internal(account_id);
+        let is_removed = lockups.remove(&index).is_some();
+        if lockups.is_empty() {
+            self.account_lockups.remove(account_id);
+            if let Some(storage) = self.account_lockups_storage.remove(account_id) {
+                self.refund_storage_internal(&storage);
+            }
+        } else {
+            self.account_lockups.insert(account_id, &lockups);
+        }
+        is_removed
+    }
+
+    fn migrate_account_lockups_internal(
+        &mut self,
+        account_id: &AccountId,
+    ) -> TreeMap<LockupIndex, ()> {
+        if let Some(lockups) = self.account_lockups.get(account_id) {
+            return lockups;
+        }
+        let mut lockups = TreeMap::new(StorageKey::AccountLockupSet {
+            hash: env::sha256(account_id.as_bytes()),
+        });
+        if let Some(legacy) = self.legacy_account_lockups.remove(account_id) {
+            for index in legacy {
+                lockups.insert(&index, &());
+            }
+        }
+        lockups
+    }
+}
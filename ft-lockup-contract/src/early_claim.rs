@@ -0,0 +1,304 @@
+//! Early claims of lockups.
+//!
+//! The unvested tokens are released with a penalty in basis points
+//! `min_penalty_bps + (max_penalty_bps - min_penalty_bps) * (time_left / lockup_duration) ^ exponent`,
+//! the vested tokens are released without the penalty.
+use crate::*;
+use near_sdk::{ext_contract, serde_json, PromiseResult, Timestamp};
+
+/// Basis points of the whole amount
+pub const FULL_PENALTY_BPS: u16 = 10_000;
+/// Max power of the penalty curve
+pub const MAX_PENALTY_EXPONENT: u8 = 4;
+pub const GAS_FOR_PENALTY_TRANSFER: Gas = Gas(50_000_000_000_000);
+pub const GAS_FOR_PENALTY_CALLBACK: Gas = Gas(10_000_000_000_000);
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EarlyClaimConfig {
+    /// Penalty of the lockup that is about to unlock
+    pub min_penalty_bps: u16,
+    /// Penalty of the lockup that has just started
+    pub max_penalty_bps: u16,
+    /// Power of the penalty curve, linear if 1
+    pub exponent: u8,
+    /// Account to receive the penalties
+    pub penalty_receiver_id: AccountId,
+    /// The penalty is sent by `ft_transfer_call` with the message if set, e.g. `"AddToPool"`
+    #[serde(default)]
+    pub penalty_msg: Option<String>,
+}
+
+impl EarlyClaimConfig {
+    pub fn assert_valid(&self) {
+        require!(
+            self.min_penalty_bps <= self.max_penalty_bps
+                && self.max_penalty_bps <= FULL_PENALTY_BPS,
+            format!("The penalty should be from min to max, both up to {FULL_PENALTY_BPS} bps")
+        );
+        require!(
+            self.exponent > 0 && self.exponent <= MAX_PENALTY_EXPONENT,
+            format!("The exponent should be from 1 to {MAX_PENALTY_EXPONENT}")
+        );
+    }
+
+    /// Penalty for `amount` of tokens with `time_left` of `duration` till the unlock.
+    pub fn penalty(&self, amount: Balance, time_left: Timestamp, duration: Timestamp) -> Balance {
+        let full = u128::from(FULL_PENALTY_BPS);
+        let ratio = if duration == 0 {
+            full
+        } else {
+            u128::from(time_left.min(duration)) * full / u128::from(duration)
+        };
+        let curve = (0..self.exponent).fold(full, |curve, _| curve * ratio / full);
+        let min = u128::from(self.min_penalty_bps);
+        let max = u128::from(self.max_penalty_bps);
+        let penalty_bps = min + (max - min) * curve / full;
+        amount / full * penalty_bps + amount % full * penalty_bps / full
+    }
+}
+
+#[ext_contract(ext_self)]
+#[allow(dead_code)]
+trait SelfCallback {
+    fn lockup_claim_early_callback(
+        &mut self,
+        index: LockupIndex,
+        account_id: AccountId,
+        amount: U128,
+        penalty: U128,
+        config: EarlyClaimConfig,
+    );
+
+    fn lockup_penalty_callback(&mut self, penalty: U128);
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Claim all the remaining tokens of the lockup before the unlock,
+    /// the penalty for the unvested tokens is sent to the penalty receiver.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Early claims must be enabled by the owner.
+    /// * The lockup must belong to the caller, be early claimable and not revocable.
+    #[payable]
+    pub fn claim_early(&mut self, index: LockupIndex) -> Promise {
+        assert_one_yocto();
+        let config = self
+            .early_claim_config
+            .clone()
+            .unwrap_or_else(|| env::panic_str("Early claim is disabled"));
+        let account_id = env::predecessor_account_id();
+        require!(
+            self.account_has_lockup(&account_id, index),
+            "No such lockup for this account"
+        );
+
+        let mut lockup = self
+            .get_lockup_internal(index)
+            .unwrap_or_else(|| env::panic_str("No such lockup for this account"));
+        require!(
+            lockup.is_early_claimable,
+            "The lockup can't be claimed early"
+        );
+        require!(
+            lockup.revocation.is_none(),
+            "Revocable lockup can't be claimed early"
+        );
+        require!(!lockup.is_claimed, "Lockup already claimed");
+
+        let now = env::block_timestamp();
+        let unvested = lockup.amount - lockup.vested_amount();
+        require!(unvested > 0, "Lockup is unlocked, use claim instead");
+        let start = lockup
+            .schedule
+            .as_ref()
+            .map(|schedule| schedule.start)
+            .or(lockup.created_at);
+        let time_left = lockup.unlock_on.saturating_sub(now);
+        // The max penalty if the start is unknown
+        let duration = start
+            .map(|start| lockup.unlock_on.saturating_sub(start))
+            .unwrap_or(time_left);
+        let penalty = config.penalty(unvested, time_left, duration);
+        let amount = lockup.remaining_amount() - penalty;
+        require!(amount > 0, "Nothing to claim after the penalty");
+
+        lockup.is_claimed = true;
+        self.save_lockup_internal(index, &lockup);
+
+        ext_ft_core::ext(self.token_account_id.clone())
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .with_attached_deposit(ONE_YOCTO)
+            .ft_transfer(account_id.clone(), amount.into(), None)
+            .then(
+                ext_self::ext(env::current_account_id())
+                    .with_static_gas(Gas(GAS_FOR_CLAIM_CALLBACK.0
+                        + GAS_FOR_PENALTY_TRANSFER.0
+                        + GAS_FOR_PENALTY_CALLBACK.0))
+                    .lockup_claim_early_callback(
+                        index,
+                        account_id,
+                        amount.into(),
+                        penalty.into(),
+                        config,
+                    ),
+            )
+    }
+
+    /// Disables early claims if `config` is `None`.
+    #[payable]
+    pub fn set_early_claim_config(&mut self, config: Option<EarlyClaimConfig>) {
+        assert_one_yocto();
+        self.assert_owner();
+        if let Some(config) = &config {
+            config.assert_valid();
+        }
+        self.early_claim_config = config;
+    }
+
+    pub fn get_early_claim_config(&self) -> Option<EarlyClaimConfig> {
+        self.early_claim_config.clone()
+    }
+
+    /// Send the penalties that failed to reach the penalty receiver to `account_id`.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Caller must be the owner.
+    #[payable]
+    pub fn withdraw_unsent_penalties(&mut self, account_id: AccountId) -> Promise {
+        assert_one_yocto();
+        self.assert_owner();
+        let amount = self.unsent_penalties;
+        require!(amount > 0, "No unsent penalties");
+        self.unsent_penalties = 0;
+
+        ext_ft_core::ext(self.token_account_id.clone())
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .with_attached_deposit(ONE_YOCTO)
+            .ft_transfer(account_id, amount.into(), None)
+            .then(
+                ext_self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_PENALTY_CALLBACK)
+                    .lockup_penalty_callback(amount.into()),
+            )
+    }
+
+    /// Penalties that failed to reach the penalty receiver
+    pub fn get_unsent_penalties(&self) -> U128 {
+        self.unsent_penalties.into()
+    }
+}
+
+#[near_bindgen]
+impl SelfCallback for Contract {
+    /// Removes the lockup and sends the penalty on the success,
+    /// or unlocks the lockup for the next claim on the failure.
+    #[private]
+    fn lockup_claim_early_callback(
+        &mut self,
+        index: LockupIndex,
+        account_id: AccountId,
+        amount: U128,
+        penalty: U128,
+        config: EarlyClaimConfig,
+    ) {
+        let mut lockup = self
+            .get_lockup_internal(index)
+            .unwrap_or_else(|| env::panic_str("No such lockup for this account"));
+
+        match env::promise_result(0) {
+            PromiseResult::NotReady => env::abort(),
+            PromiseResult::Successful(_) => {
+                events::LockupClaimEarly {
+                    index,
+                    account_id: &account_id,
+                    amount,
+                    penalty,
+                    penalty_receiver_id: &config.penalty_receiver_id,
+                }
+                .emit();
+                self.remove_lockup_internal(index, lockup);
+                self.remove_account_lockup_internal(&account_id, index);
+
+                if penalty.0 > 0 {
+                    let token = ext_ft_core::ext(self.token_account_id.clone())
+                        .with_attached_deposit(ONE_YOCTO);
+                    match config.penalty_msg {
+                        Some(msg) => token
+                            .with_static_gas(GAS_FOR_PENALTY_TRANSFER)
+                            .ft_transfer_call(config.penalty_receiver_id, penalty, None, msg),
+                        None => token.with_static_gas(GAS_FOR_FT_TRANSFER).ft_transfer(
+                            config.penalty_receiver_id,
+                            penalty,
+                            None,
+                        ),
+                    }
+                    .then(
+                        ext_self::ext(env::current_account_id())
+                            .with_static_gas(GAS_FOR_PENALTY_CALLBACK)
+                            .lockup_penalty_callback(penalty),
+                    );
+                }
+            }
+            PromiseResult::Failed => {
+                lockup.is_claimed = false;
+                self.save_lockup_internal(index, &lockup);
+            }
+        }
+    }
+
+    /// Handles both `ft_transfer` and `ft_transfer_call` results.
+    /// The part of the penalty that is not sent can be withdrawn by the owner.
+    #[private]
+    fn lockup_penalty_callback(&mut self, penalty: U128) {
+        let sent = match env::promise_result(0) {
+            PromiseResult::NotReady => env::abort(),
+            // `ft_transfer` returns nothing and transfers the whole amount
+            PromiseResult::Successful(value) if value.is_empty() => penalty.0,
+            PromiseResult::Successful(value) => serde_json::from_slice::<U128>(&value)
+                .map(|used| used.0)
+                .unwrap_or_default(),
+            PromiseResult::Failed => 0,
+        }
+        .min(penalty.0);
+
+        if sent < penalty.0 {
+            self.unsent_penalties = self
+                .unsent_penalties
+                .checked_add(penalty.0 - sent)
+                .unwrap_or_else(|| env::panic_str("Add will overflow"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(exponent: u8) -> EarlyClaimConfig {
+        EarlyClaimConfig {
+            min_penalty_bps: 1_000,
+            max_penalty_bps: 5_000,
+            exponent,
+            penalty_receiver_id: AccountId::new_unchecked("pool.near".to_owned()),
+            penalty_msg: None,
+        }
+    }
+
+    #[test]
+    fn penalty_curve() {
+        let linear = config(1);
+        assert_eq!(linear.penalty(1_000_000, 100, 100), 500_000);
+        assert_eq!(linear.penalty(1_000_000, 50, 100), 300_000);
+        assert_eq!(linear.penalty(1_000_000, 0, 100), 100_000);
+        // Unknown start
+        assert_eq!(linear.penalty(1_000_000, 0, 0), 500_000);
+
+        let quadratic = config(2);
+        assert_eq!(quadratic.penalty(1_000_000, 50, 100), 200_000);
+        assert_eq!(quadratic.penalty(u128::MAX, 100, 100), u128::MAX / 2);
+    }
+}
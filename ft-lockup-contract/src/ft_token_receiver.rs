@@ -1,10 +1,185 @@
 use crate::*;
+use near_sdk::{log, Timestamp};
+use revoke::Revocation;
+use storage_impl::LockupStorage;
+use vesting::{VestingSchedule, VestingScheduleView};
 
+/// Optional terms of a lockup
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LockupOptions {
+    /// Reason of the lockup
+    #[serde(default)]
+    pub memo: Option<String>,
+    /// Account that can revoke the unvested tokens, not revocable if `None`
+    #[serde(default)]
+    pub revocable_by: Option<AccountId>,
+    /// Account to receive the revoked tokens, `revocable_by` if `None`
+    #[serde(default)]
+    pub refund_account_id: Option<AccountId>,
+    /// The beneficiary can transfer the lockup.
+    /// If not set, only not revocable lockups are transferable.
+    /// Lockups created before the transfers are not transferable.
+    #[serde(default)]
+    pub transferable: Option<bool>,
+    /// The beneficiary can claim the lockup early with a penalty, `true` if not set
+    #[serde(default)]
+    pub early_claimable: Option<bool>,
+}
+
+/// Lockup of the batch, all the tokens unlock after `duration`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchLockup {
+    pub account_id: AccountId,
+    pub amount: U128,
+    pub duration: U64,
+}
+
+/// Lockup message of `ft_transfer_call`, e.g.
+/// `{"V1": {"Lockup": {"account_id": "alice.near", "duration": "604800000000000"}}}`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum FtMessage {
+    /// All the tokens unlock after `duration`
+    Lockup {
+        account_id: AccountId,
+        duration: U64,
+        #[serde(flatten)]
+        options: LockupOptions,
+    },
+    /// The tokens unlock by the vesting schedule
+    Vesting {
+        account_id: AccountId,
+        schedule: VestingScheduleView,
+        #[serde(flatten)]
+        options: LockupOptions,
+    },
+    /// Many lockups, the amounts must sum up to the transferred amount
+    Batch(Vec<BatchLockup>),
+}
+
+/// Lockup to create from the message
+struct NewLockup {
+    account_id: AccountId,
+    amount: Balance,
+    unlock_on: Timestamp,
+    schedule: Option<VestingSchedule>,
+    options: LockupOptions,
+}
+
+fn unlock_after(duration: U64) -> Timestamp {
+    require!(duration.0 > 0, "The duration should be positive");
+    env::block_timestamp()
+        .checked_add(duration.0)
+        .unwrap_or_else(|| env::panic_str("The duration is too long"))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum VersionedFtMessage {
+    V1(FtMessage),
+}
+
+/// Message before the versioning, exactly one of `duration` and `schedule` is expected.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
-pub struct FtMessage {
-    duration: U64,
+pub struct LegacyFtMessage {
+    #[serde(default)]
+    duration: Option<U64>,
     account_id: AccountId,
+    #[serde(default)]
+    schedule: Option<VestingScheduleView>,
+    #[serde(flatten)]
+    options: LockupOptions,
+}
+
+impl From<LegacyFtMessage> for FtMessage {
+    fn from(message: LegacyFtMessage) -> Self {
+        match (message.duration, message.schedule) {
+            (Some(duration), None) => Self::Lockup {
+                account_id: message.account_id,
+                duration,
+                options: message.options,
+            },
+            (None, Some(schedule)) => Self::Vesting {
+                account_id: message.account_id,
+                schedule,
+                options: message.options,
+            },
+            _ => env::panic_str("Expected either duration or schedule"),
+        }
+    }
+}
+
+impl From<VersionedFtMessage> for FtMessage {
+    fn from(message: VersionedFtMessage) -> Self {
+        match message {
+            VersionedFtMessage::V1(message) => message,
+        }
+    }
+}
+
+impl FtMessage {
+    /// Parse the versioned message, or the legacy one with `account_id` at the top level.
+    pub fn parse(msg: &str) -> Self {
+        let value: serde_json::Value = serde_json::from_str(msg)
+            .unwrap_or_else(|err| env::panic_str(&format!("Invalid lockup message: {err}")));
+        if value.get("account_id").is_some() {
+            serde_json::from_value::<LegacyFtMessage>(value).map(Into::into)
+        } else {
+            serde_json::from_value::<VersionedFtMessage>(value).map(Into::into)
+        }
+        .unwrap_or_else(|err| env::panic_str(&format!("Invalid lockup message: {err}")))
+    }
+}
+
+/// Limits of the new lockups, the transfer is refunded if a lockup is out of them
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LockupLimits {
+    /// Min time till the unlock of all the tokens
+    #[serde(default)]
+    pub min_duration: Option<U64>,
+    /// Max time till the unlock of all the tokens
+    #[serde(default)]
+    pub max_duration: Option<U64>,
+    #[serde(default)]
+    pub min_amount: Option<U128>,
+}
+
+impl LockupLimits {
+    pub fn assert_valid(&self) {
+        if let (Some(min), Some(max)) = (self.min_duration, self.max_duration) {
+            require!(
+                min.0 <= max.0,
+                "The min duration should not exceed the max duration"
+            );
+        }
+    }
+
+    /// Returns the reason of the refund if the lockup is out of the limits.
+    pub fn check(&self, amount: Balance, duration: Timestamp) -> Result<(), String> {
+        if let Some(min_amount) = self.min_amount.filter(|min| amount < min.0) {
+            return Err(format!(
+                "The amount {amount} is less than the min amount {}",
+                min_amount.0
+            ));
+        }
+        if let Some(min_duration) = self.min_duration.filter(|min| duration < min.0) {
+            return Err(format!(
+                "The duration {duration} is less than the min duration {}",
+                min_duration.0
+            ));
+        }
+        if let Some(max_duration) = self.max_duration.filter(|max| duration > max.0) {
+            return Err(format!(
+                "The duration {duration} is greater than the max duration {}",
+                max_duration.0
+            ));
+        }
+        Ok(())
+    }
 }
 
 #[near_bindgen]
@@ -20,29 +195,219 @@ impl FungibleTokenReceiver for Contract {
             self.token_account_id,
             "Invalid token ID"
         );
-        // let amount = amount.into();
         self.assert_deposit_whitelist(&sender_id);
 
-        let ft_message: FtMessage = serde_json::from_str(&msg).unwrap();
+        let lockups = match FtMessage::parse(&msg) {
+            FtMessage::Lockup {
+                account_id,
+                duration,
+                options,
+            } => vec![NewLockup {
+                account_id,
+                amount: amount.0,
+                unlock_on: unlock_after(duration),
+                schedule: None,
+                options,
+            }],
+            FtMessage::Vesting {
+                account_id,
+                schedule,
+                options,
+            } => {
+                let schedule = VestingSchedule::from(schedule);
+                vec![NewLockup {
+                    account_id,
+                    amount: amount.0,
+                    unlock_on: schedule.end(),
+                    schedule: Some(schedule),
+                    options,
+                }]
+            }
+            FtMessage::Batch(batch) => {
+                self.assert_batch(&batch, amount.0);
+                batch
+                    .into_iter()
+                    .map(|lockup| NewLockup {
+                        account_id: lockup.account_id,
+                        amount: lockup.amount.0,
+                        unlock_on: unlock_after(lockup.duration),
+                        schedule: None,
+                        options: Default::default(),
+                    })
+                    .collect()
+            }
+        };
+
+        for lockup in &lockups {
+            let duration = lockup.unlock_on.saturating_sub(env::block_timestamp());
+            if let Err(reason) = self.lockup_limits.check(lockup.amount, duration) {
+                log!("Refund the lockup: {}", reason);
+                return PromiseOrValue::Value(amount);
+            }
+        }
+
+        for lockup in lockups {
+            self.create_lockup_internal(
+                &sender_id,
+                &lockup.account_id,
+                lockup.amount,
+                lockup.unlock_on,
+                lockup.schedule,
+                lockup.options,
+            );
+        }
+
+        PromiseOrValue::Value(U128(0))
+    }
+}
+
+impl Contract {
+    /// The batch must fit into the gas and sum up to the transferred `amount`.
+    fn assert_batch(&self, batch: &[BatchLockup], amount: Balance) {
+        require!(
+            !batch.is_empty() && batch.len() <= MAX_BATCH_SIZE,
+            format!("The batch size should be from 1 to {MAX_BATCH_SIZE}")
+        );
+        let required_gas = GAS_FOR_BATCH_LOCKUP.0 * batch.len() as u64;
+        let available_gas = env::prepaid_gas().0.saturating_sub(env::used_gas().0);
+        require!(
+            available_gas >= required_gas,
+            format!(
+                "Not enough gas for {} lockups, {} is required",
+                batch.len(),
+                required_gas
+            )
+        );
+
+        let total = batch.iter().fold(0, |total: Balance, lockup| {
+            require!(lockup.amount.0 > 0, "The lockup amount should be positive");
+            total
+                .checked_add(lockup.amount.0)
+                .unwrap_or_else(|| env::panic_str("Add will overflow"))
+        });
+        require!(
+            total == amount,
+            format!("The batch total {total} doesn't match the transferred amount {amount}")
+        );
+    }
+
+    /// Store the new lockup and charge `creator` for its storage.
+    pub fn create_lockup_internal(
+        &mut self,
+        creator: &AccountId,
+        account_id: &AccountId,
+        amount: Balance,
+        unlock_on: Timestamp,
+        schedule: Option<VestingSchedule>,
+        options: LockupOptions,
+    ) -> LockupIndex {
+        if let Some(memo) = &options.memo {
+            require!(memo.len() <= MAX_MEMO_LENGTH, "Memo is too long");
+        }
+        require!(
+            options.refund_account_id.is_none() || options.revocable_by.is_some(),
+            "Refund account of not revocable lockup"
+        );
+        // The revoker must agree to the transfer explicitly
+        let is_transferable = options
+            .transferable
+            .unwrap_or(options.revocable_by.is_none());
+        let revocation = options.revocable_by.map(|revocable_by| Revocation {
+            refund_account_id: options
+                .refund_account_id
+                .unwrap_or_else(|| revocable_by.clone()),
+            revocable_by,
+            revoked_at: None,
+        });
 
         let index = self.next_index();
         let lockup = Lockup {
-            amount: amount.0,
-            unlock_on: env::block_timestamp()
-                .checked_add(ft_message.duration.0)
-                .unwrap_or_else(|| env::panic_str("Add will overflow")),
+            amount,
+            unlock_on,
             is_claimed: false,
+            creator: Some(creator.clone()),
+            created_at: Some(env::block_timestamp()),
+            memo: options.memo,
+            schedule,
+            claimed_amount: 0,
+            revocation,
+            is_transferable,
+            storage: Some(LockupStorage {
+                payer_id: creator.clone(),
+                bytes: 0,
+            }),
+            is_early_claimable: options.early_claimable.unwrap_or(true),
         };
+        self.create_account_lockups_internal(account_id, creator);
+        let initial_storage_usage = env::storage_usage();
+        self.add_account_lockup_internal(account_id, index);
 
-        self.lockups.insert(&index, &lockup);
-        let mut account_lockups = self
-            .account_lockups
-            .get(&ft_message.account_id)
-            .unwrap_or_default();
-        account_lockups.insert(index);
-        self.account_lockups
-            .insert(&ft_message.account_id, &account_lockups);
+        events::LockupCreate {
+            index,
+            account_id,
+            amount: amount.into(),
+            unlock_on: lockup.unlock_on.into(),
+            creator,
+            memo: lockup.memo.as_deref(),
+        }
+        .emit();
+        self.save_lockup_internal(index, &lockup);
+        self.charge_lockup_storage_internal(index, creator, initial_storage_usage);
 
-        PromiseOrValue::Value(U128(0))
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_messages() {
+        let message = FtMessage::parse(
+            r#"{"V1": {"Lockup": {"account_id": "alice.near", "duration": "100", "memo": "Airdrop"}}}"#,
+        );
+        assert!(matches!(
+            message,
+            FtMessage::Lockup {
+                duration: U64(100),
+                options: LockupOptions { memo: Some(_), .. },
+                ..
+            }
+        ));
+
+        let message = FtMessage::parse(
+            r#"{"V1": {"Vesting": {"account_id": "alice.near", "schedule": {"duration": "100"}}}}"#,
+        );
+        assert!(matches!(message, FtMessage::Vesting { .. }));
+
+        let message = FtMessage::parse(
+            r#"{"V1": {"Batch": [{"account_id": "alice.near", "amount": "10", "duration": "100"}]}}"#,
+        );
+        assert!(matches!(message, FtMessage::Batch(batch) if batch.len() == 1));
+
+        let message = FtMessage::parse(r#"{"account_id": "alice.near", "duration": "100"}"#);
+        assert!(matches!(
+            message,
+            FtMessage::Lockup {
+                duration: U64(100),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn check_limits() {
+        let limits = LockupLimits {
+            min_duration: Some(U64(10)),
+            max_duration: Some(U64(100)),
+            min_amount: Some(U128(1_000)),
+        };
+        assert!(limits.check(1_000, 10).is_ok());
+        assert!(limits.check(1_000, 100).is_ok());
+        assert!(limits.check(999, 50).is_err());
+        assert!(limits.check(1_000, 9).is_err());
+        assert!(limits.check(1_000, 101).is_err());
+        assert!(LockupLimits::default().check(0, 0).is_ok());
     }
 }
@@ -1,18 +1,28 @@
-use near_contract_standards::{fungible_token::receiver::FungibleTokenReceiver, upgrade::Ownable};
+use common::events::{self, RealisEvent};
+use near_contract_standards::{
+    fungible_token::{core::ext_ft_core, receiver::FungibleTokenReceiver},
+    upgrade::Ownable,
+};
 use near_sdk::{
     assert_one_yocto,
     borsh::{self, maybestd::collections::HashSet, BorshDeserialize, BorshSerialize},
-    collections::{LookupMap, UnorderedMap, UnorderedSet},
+    collections::{LookupMap, TreeMap, UnorderedMap, UnorderedSet},
     env,
     json_types::{U128, U64},
     near_bindgen, require,
     serde::{Deserialize, Serialize},
-    AccountId, BorshStorageKey, Gas, PanicOnDefault, Promise, PromiseOrValue,
+    AccountId, Balance, BorshStorageKey, Gas, PanicOnDefault, Promise, PromiseOrValue, ONE_YOCTO,
 };
 
+pub mod account_lockups;
+pub mod early_claim;
 pub mod ft_token_receiver;
 pub mod lockup;
+pub mod revoke;
+pub mod storage_impl;
+pub mod transfer;
 pub mod update;
+pub mod vesting;
 pub mod view;
 
 use crate::lockup::*;
@@ -20,6 +30,21 @@ use crate::lockup::*;
 pub type LockupIndex = u32;
 pub const GAS_FOR_CLAIM_CALLBACK: Gas = Gas(10_000_000_000_000);
 pub const GAS_FOR_FT_TRANSFER: Gas = Gas(25_000_000_000_000);
+pub const GAS_FOR_CLAIM_ALL_CALLBACK_PER_LOCKUP: Gas = Gas(3_000_000_000_000);
+/// Max number of lockups claimed by `claim_all`
+pub const MAX_CLAIM_ALL_LIMIT: u32 = 50;
+/// Page size of the lockup views if not set
+pub const DEFAULT_PAGE_LIMIT: u32 = 100;
+/// Max number of indexes checked by a single page of the lockup views
+pub const MAX_PAGE_SCAN: usize = 1_000;
+/// Max length of the lockup memo in bytes
+pub const MAX_MEMO_LENGTH: usize = 256;
+/// Gas left to `ft_on_transfer` by `ft_transfer_call` with the max prepaid gas of 300 TGas
+pub const GAS_FOR_BATCH: Gas = Gas(250_000_000_000_000);
+/// Gas to create a single lockup of a batch, measured by `tests/batch.rs` with a margin
+pub const GAS_FOR_BATCH_LOCKUP: Gas = Gas(5_000_000_000_000);
+/// Max number of lockups in a batch message
+pub const MAX_BATCH_SIZE: usize = (GAS_FOR_BATCH.0 / GAS_FOR_BATCH_LOCKUP.0) as usize;
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
@@ -29,12 +54,28 @@ pub struct Contract {
     pub token_account_id: AccountId,
     /// Account IDs that can create new lockups.
     pub deposit_whitelist: UnorderedSet<AccountId>,
-    /// All lockups
+    /// All lockups except the legacy ones
     pub lockups: UnorderedMap<LockupIndex, Lockup>,
-    /// Lockups indexes by AccountId
-    pub account_lockups: LookupMap<AccountId, HashSet<LockupIndex>>,
+    /// Lockups indexes by AccountId in the legacy layout, see `account_lockups`
+    pub legacy_account_lockups: LookupMap<AccountId, HashSet<LockupIndex>>,
 
     pub index: LockupIndex,
+    /// Staking contract account id to restake claimed lockups
+    pub staking_account_id: Option<AccountId>,
+    /// Lockups indexes by AccountId
+    pub account_lockups: LookupMap<AccountId, TreeMap<LockupIndex, ()>>,
+    /// Storage balances of the lockup creators
+    pub storage_deposits: LookupMap<AccountId, storage_impl::StorageDeposit>,
+    /// Early claims are disabled if `None`
+    pub early_claim_config: Option<early_claim::EarlyClaimConfig>,
+    /// Limits of the new lockups
+    pub lockup_limits: ft_token_receiver::LockupLimits,
+    /// Early claim penalties that failed to reach the penalty receiver
+    pub unsent_penalties: Balance,
+    /// Storage of the account lockup sets by AccountId
+    pub account_lockups_storage: LookupMap<AccountId, storage_impl::LockupStorage>,
+    /// Lockups in the layout before the upgrade, moved to `lockups` on the first change
+    pub legacy_lockups: UnorderedMap<LockupIndex, LockupV0>,
 }
 
 #[derive(BorshStorageKey, BorshSerialize)]
@@ -42,6 +83,11 @@ pub(crate) enum StorageKey {
     Lockups,
     AccountLockups,
     DepositWhitelist,
+    AccountLockupSets,
+    AccountLockupSet { hash: Vec<u8> },
+    StorageDeposits,
+    AccountLockupsStorage,
+    LockupRecords,
 }
 
 #[near_bindgen]
@@ -54,13 +100,22 @@ impl Contract {
     ) -> Self {
         let mut deposit_whitelist_set = UnorderedSet::new(StorageKey::DepositWhitelist);
         deposit_whitelist_set.extend(deposit_whitelist.into_iter());
+        common::state_version::set_state_version(update::STATE_VERSION);
         Self {
             owner_id: owner_id.unwrap_or_else(env::predecessor_account_id),
-            lockups: UnorderedMap::new(StorageKey::Lockups),
-            account_lockups: LookupMap::new(StorageKey::AccountLockups),
+            lockups: UnorderedMap::new(StorageKey::LockupRecords),
+            legacy_account_lockups: LookupMap::new(StorageKey::AccountLockups),
             token_account_id,
             deposit_whitelist: deposit_whitelist_set,
             index: 0,
+            staking_account_id: None,
+            account_lockups: LookupMap::new(StorageKey::AccountLockupSets),
+            storage_deposits: LookupMap::new(StorageKey::StorageDeposits),
+            early_claim_config: None,
+            lockup_limits: Default::default(),
+            unsent_penalties: 0,
+            account_lockups_storage: LookupMap::new(StorageKey::AccountLockupsStorage),
+            legacy_lockups: UnorderedMap::new(StorageKey::Lockups),
         }
     }
 
@@ -68,46 +123,146 @@ impl Contract {
     pub fn claim(&mut self, index: LockupIndex, account_id: Option<AccountId>) -> Promise {
         assert_one_yocto();
         let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
-        let account_lockups = self
-            .account_lockups
-            .get(&account_id)
-            .unwrap_or_else(|| env::panic_str("No lockups found"));
         require!(
-            account_lockups.contains(&index),
+            self.account_has_lockup(&account_id, index),
             "No such lockup for this account"
         );
 
         let mut lockup = self
-            .lockups
-            .get(&index)
+            .get_lockup_internal(index)
             .unwrap_or_else(|| env::panic_str("No such lockup for this account"));
 
         let promise = lockup.claim(self.token_account_id.clone(), account_id, index);
-        self.lockups.insert(&index, &lockup);
+        self.save_lockup_internal(index, &lockup);
+
+        promise
+    }
+
+    /// Claim all the unlocked tokens of `account_id` lockups with a single transfer.
+    /// Takes at most `limit` lockups, `MAX_CLAIM_ALL_LIMIT` by default.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * `limit` must not exceed `MAX_CLAIM_ALL_LIMIT`.
+    #[payable]
+    pub fn claim_all(&mut self, account_id: Option<AccountId>, limit: Option<u32>) -> Promise {
+        assert_one_yocto();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let limit = limit.unwrap_or(MAX_CLAIM_ALL_LIMIT);
+        require!(
+            limit > 0 && limit <= MAX_CLAIM_ALL_LIMIT,
+            format!("The limit should be from 1 to {MAX_CLAIM_ALL_LIMIT}")
+        );
+
+        let mut claims = vec![];
+        let mut total: Balance = 0;
+        for index in self.get_account_lockup_indexes(&account_id, None) {
+            if claims.len() == limit as usize {
+                break;
+            }
+            let mut lockup = self
+                .get_lockup_internal(index)
+                .unwrap_or_else(|| env::panic_str("No such lockup for this account"));
+            if lockup.is_claimed || lockup.claimable_amount() == 0 {
+                continue;
+            }
+            let amount = lockup.start_claim();
+            self.save_lockup_internal(index, &lockup);
+            total += amount;
+            claims.push((index, U128(amount)));
+        }
+        require!(!claims.is_empty(), "Nothing to claim");
+
+        let callback_gas = GAS_FOR_CLAIM_CALLBACK.0
+            + GAS_FOR_CLAIM_ALL_CALLBACK_PER_LOCKUP.0 * claims.len() as u64;
+        ext_ft_core::ext(self.token_account_id.clone())
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .with_attached_deposit(ONE_YOCTO)
+            .ft_transfer(account_id.clone(), total.into(), None)
+            .then(
+                ext_self::ext(env::current_account_id())
+                    .with_static_gas(Gas(callback_gas))
+                    .lockup_claim_all_callback(account_id, claims),
+            )
+    }
+
+    /// Claim the lockup of the caller and stake the tokens for the caller.
+    /// If the staking contract refunds the tokens, they stay in the lockup.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * The staking contract must be set by the owner.
+    #[payable]
+    pub fn claim_and_restake(&mut self, index: LockupIndex) -> Promise {
+        assert_one_yocto();
+        let staking_account_id = self
+            .staking_account_id
+            .clone()
+            .unwrap_or_else(|| env::panic_str("Restake is not available"));
+        let account_id = env::predecessor_account_id();
+        require!(
+            self.account_has_lockup(&account_id, index),
+            "No such lockup for this account"
+        );
+
+        let mut lockup = self
+            .get_lockup_internal(index)
+            .unwrap_or_else(|| env::panic_str("No such lockup for this account"));
+
+        let promise = lockup.claim_and_restake(
+            self.token_account_id.clone(),
+            staking_account_id,
+            account_id,
+            index,
+        );
+        self.save_lockup_internal(index, &lockup);
 
         promise
     }
 
+    #[payable]
+    pub fn set_staking_account_id(&mut self, staking_account_id: Option<AccountId>) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.staking_account_id = staking_account_id;
+    }
+
+    #[payable]
+    pub fn set_lockup_limits(&mut self, limits: ft_token_receiver::LockupLimits) {
+        assert_one_yocto();
+        self.assert_owner();
+        limits.assert_valid();
+        self.lockup_limits = limits;
+    }
+
     #[payable]
     pub fn extend_deposit_whitelist(&mut self, account_ids: Vec<AccountId>) {
         assert_one_yocto();
         self.assert_owner();
-        self.deposit_whitelist.extend(account_ids.into_iter())
+        events::DepositWhitelistExtend {
+            account_ids: &account_ids,
+        }
+        .emit();
+        self.deposit_whitelist.extend(account_ids.into_iter());
     }
 
     #[payable]
     pub fn reduce_deposit_whitelist(&mut self, account_ids: Vec<AccountId>) {
         assert_one_yocto();
         self.assert_owner();
-        account_ids.into_iter().for_each(|account_id| {
-            self.deposit_whitelist.remove(&account_id);
+        account_ids.iter().for_each(|account_id| {
+            self.deposit_whitelist.remove(account_id);
         });
+        events::DepositWhitelistReduce {
+            account_ids: &account_ids,
+        }
+        .emit();
     }
 }
 
 impl Contract {
     pub fn next_index(&mut self) -> LockupIndex {
-        while self.lockups.get(&self.index).is_some() {
+        while self.get_lockup_internal(self.index).is_some() {
             self.index = self
                 .index
                 .checked_add(1)
@@ -116,6 +271,20 @@ impl Contract {
         self.index
     }
 
+    pub fn get_lockup_internal(&self, index: LockupIndex) -> Option<Lockup> {
+        self.lockups
+            .get(&index)
+            .or_else(|| self.legacy_lockups.get(&index).map(Into::into))
+    }
+
+    /// Store the lockup in the current layout, moving it from the legacy lockups.
+    pub fn save_lockup_internal(&mut self, index: LockupIndex, lockup: &Lockup) {
+        self.lockups.insert(&index, lockup);
+        if !self.legacy_lockups.is_empty() {
+            self.legacy_lockups.remove(&index);
+        }
+    }
+
     pub fn assert_deposit_whitelist(&self, account_id: &AccountId) {
         require!(
             self.deposit_whitelist.contains(account_id),
@@ -1,32 +1,143 @@
+use common::events::{self, RealisEvent};
 use near_contract_standards::fungible_token::core::ext_ft_core;
 use near_sdk::{
     borsh::{self, BorshDeserialize, BorshSerialize},
-    env, ext_contract, near_bindgen, require, AccountId, Balance, Promise, PromiseResult,
-    Timestamp, ONE_YOCTO,
+    env, ext_contract,
+    json_types::U128,
+    near_bindgen, require,
+    serde_json::{self, json},
+    AccountId, Balance, Promise, PromiseResult, Timestamp, ONE_YOCTO,
 };
 
-use crate::{Contract, ContractExt, LockupIndex, GAS_FOR_CLAIM_CALLBACK, GAS_FOR_FT_TRANSFER};
+use crate::{
+    revoke::Revocation, storage_impl::LockupStorage, vesting::VestingSchedule, Contract,
+    ContractExt, LockupIndex, GAS_FOR_CLAIM_CALLBACK, GAS_FOR_FT_TRANSFER,
+};
+
+/// Lockup layout deployed before the versioning.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LockupV0 {
+    pub amount: Balance,
+    pub unlock_on: Timestamp,
+    pub is_claimed: bool,
+}
+
+impl From<LockupV0> for Lockup {
+    fn from(lockup: LockupV0) -> Self {
+        Self {
+            amount: lockup.amount,
+            unlock_on: lockup.unlock_on,
+            is_claimed: lockup.is_claimed,
+            creator: None,
+            created_at: None,
+            memo: None,
+            schedule: None,
+            claimed_amount: 0,
+            revocation: None,
+            // The creators haven't agreed to the transfers of the existing lockups
+            is_transferable: false,
+            storage: None,
+            is_early_claimable: true,
+        }
+    }
+}
 
 #[derive(BorshDeserialize, BorshSerialize, Eq, PartialEq, PartialOrd, Hash)]
 pub struct Lockup {
+    /// Total amount of the lockup
     pub amount: Balance,
+    /// All the tokens are unlocked since this moment
     pub unlock_on: Timestamp,
+    /// A claim is in progress
     pub is_claimed: bool,
+    /// Account that created the lockup, unknown for lockups created before the metadata
+    pub creator: Option<AccountId>,
+    pub created_at: Option<Timestamp>,
+    /// Reason of the lockup given by the creator
+    pub memo: Option<String>,
+    /// Gradual unlock, all the tokens unlock at `unlock_on` if `None`
+    pub schedule: Option<VestingSchedule>,
+    pub claimed_amount: Balance,
+    /// Terms of the revocation, not revocable if `None`
+    pub revocation: Option<Revocation>,
+    /// The beneficiary can transfer the lockup to another account
+    pub is_transferable: bool,
+    /// Storage paid by the creator, paid by the contract for lockups created before the storage deposits
+    pub storage: Option<LockupStorage>,
+    /// The beneficiary can claim the unvested tokens with a penalty
+    pub is_early_claimable: bool,
 }
 
 impl Lockup {
+    /// Tokens vested so far, the vesting stops at the revocation.
+    pub fn vested_amount(&self) -> Balance {
+        let now = match self.revocation.as_ref().and_then(|r| r.revoked_at) {
+            Some(revoked_at) => revoked_at.min(env::block_timestamp()),
+            None => env::block_timestamp(),
+        };
+        match &self.schedule {
+            Some(schedule) => schedule.vested_amount(self.amount, now),
+            None if self.unlock_on <= now => self.amount,
+            None => 0,
+        }
+    }
+
+    /// Vested tokens that are not claimed yet
+    pub fn claimable_amount(&self) -> Balance {
+        self.vested_amount().saturating_sub(self.claimed_amount)
+    }
+
+    /// Tokens left for the beneficiary, the unvested tokens are returned on the revocation.
+    pub fn remaining_amount(&self) -> Balance {
+        let total = if self.is_revoked() {
+            self.vested_amount()
+        } else {
+            self.amount
+        };
+        total - self.claimed_amount
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.revocation
+            .as_ref()
+            .map(|r| r.revoked_at.is_some())
+            .unwrap_or_default()
+    }
+
     pub fn claim(
         &mut self,
         token_id: AccountId,
         account_id: AccountId,
         index: LockupIndex,
     ) -> Promise {
-        require!(!self.is_claimed, "Lockup already claimed");
-        require!(
-            self.unlock_on <= near_sdk::env::block_timestamp(),
-            "Lockup isn't expired"
-        );
-        self.is_claimed = true;
+        let amount = self.start_claim();
+
+        ext_ft_core::ext(token_id)
+            .with_static_gas(
+                env::prepaid_gas()
+                    .0
+                    .checked_sub(GAS_FOR_FT_TRANSFER.0)
+                    .unwrap_or_else(|| env::panic_str("Sub will overflow"))
+                    .into(),
+            )
+            .with_attached_deposit(ONE_YOCTO)
+            .ft_transfer(account_id.clone(), amount.into(), None)
+            .then(
+                ext_self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_CLAIM_CALLBACK)
+                    .lockup_claim_callback(index, account_id, Some(amount.into())),
+            )
+    }
+
+    /// Claim tokens to the staking contract and stake them for `account_id`.
+    pub fn claim_and_restake(
+        &mut self,
+        token_id: AccountId,
+        staking_id: AccountId,
+        account_id: AccountId,
+        index: LockupIndex,
+    ) -> Promise {
+        let amount = self.start_claim();
 
         ext_ft_core::ext(token_id)
             .with_static_gas(
@@ -37,43 +148,175 @@ impl Lockup {
                     .into(),
             )
             .with_attached_deposit(ONE_YOCTO)
-            .ft_transfer(account_id.clone(), self.amount.into(), None)
+            .ft_transfer_call(
+                staking_id,
+                amount.into(),
+                None,
+                json!({ "StakeFor": { "account_id": account_id } }).to_string(),
+            )
             .then(
                 ext_self::ext(env::current_account_id())
                     .with_static_gas(GAS_FOR_CLAIM_CALLBACK)
-                    .lockup_claim_callback(index, account_id),
+                    .lockup_claim_callback(index, account_id, Some(amount.into())),
             )
     }
+
+    /// Lock the lockup until the claim callback and return the amount to claim.
+    pub fn start_claim(&mut self) -> Balance {
+        require!(!self.is_claimed, "Lockup already claimed");
+        require!(
+            self.schedule.is_some() || self.unlock_on <= env::block_timestamp(),
+            "Lockup isn't expired"
+        );
+        let amount = self.claimable_amount();
+        require!(amount > 0, "Nothing is vested yet");
+        self.is_claimed = true;
+        amount
+    }
 }
 
 #[ext_contract(ext_self)]
 trait SelfCallback {
-    fn lockup_claim_callback(&mut self, index: LockupIndex, account_id: AccountId);
+    fn lockup_claim_callback(
+        &mut self,
+        index: LockupIndex,
+        account_id: AccountId,
+        amount: Option<U128>,
+    );
+    fn lockup_claim_all_callback(
+        &mut self,
+        account_id: AccountId,
+        claims: Vec<(LockupIndex, U128)>,
+    );
 }
 
 #[near_bindgen]
 impl SelfCallback for Contract {
+    /// Handles both `ft_transfer` and `ft_transfer_call` results.
+    /// The part of the tokens refunded by `ft_transfer_call` stays in the lockup.
+    /// `amount` is the claimed amount, all the remaining tokens if `None`.
     #[private]
-    fn lockup_claim_callback(&mut self, index: LockupIndex, account_id: AccountId) {
-        match env::promise_result(0) {
+    fn lockup_claim_callback(
+        &mut self,
+        index: LockupIndex,
+        account_id: AccountId,
+        amount: Option<U128>,
+    ) {
+        let lockup = self
+            .get_lockup_internal(index)
+            .unwrap_or_else(|| env::panic_str("No such lockup for this account"));
+        let amount = amount
+            .map(|a| a.0)
+            .unwrap_or_else(|| lockup.remaining_amount());
+        let used = match env::promise_result(0) {
             PromiseResult::NotReady => env::abort(),
-            PromiseResult::Successful(_) => {
-                self.lockups.remove(&index);
-                let mut account_lockups = self
-                    .account_lockups
-                    .get(&account_id)
-                    .unwrap_or_else(|| env::panic_str("No lockups found"));
-                account_lockups.remove(&index);
-                self.account_lockups.insert(&account_id, &account_lockups);
+            // `ft_transfer` returns nothing and transfers the whole amount
+            PromiseResult::Successful(value) if value.is_empty() => amount,
+            PromiseResult::Successful(value) => serde_json::from_slice::<U128>(&value)
+                .map(|used| used.0)
+                .unwrap_or_default(),
+            PromiseResult::Failed => 0,
+        }
+        .min(amount);
+
+        if self.finish_claim_internal(index, lockup, &account_id, used) {
+            self.remove_account_lockup_internal(&account_id, index);
+        }
+    }
+
+    /// Finishes the claim of all the lockups on the transfer success,
+    /// or unlocks them for the next claim on the failure.
+    #[private]
+    fn lockup_claim_all_callback(
+        &mut self,
+        account_id: AccountId,
+        claims: Vec<(LockupIndex, U128)>,
+    ) {
+        let is_success = match env::promise_result(0) {
+            PromiseResult::NotReady => env::abort(),
+            PromiseResult::Successful(_) => true,
+            PromiseResult::Failed => false,
+        };
+
+        for (index, amount) in claims {
+            let lockup = self
+                .get_lockup_internal(index)
+                .unwrap_or_else(|| env::panic_str("No such lockup for this account"));
+            let used = if is_success { amount.0 } else { 0 };
+            if self.finish_claim_internal(index, lockup, &account_id, used) {
+                self.remove_account_lockup_internal(&account_id, index);
             }
-            PromiseResult::Failed => {
-                let mut lockup = self
-                    .lockups
-                    .get(&index)
-                    .unwrap_or_else(|| env::panic_str("No such lockup for this account"));
-                lockup.is_claimed = false;
-                self.lockups.insert(&index, &lockup);
+        }
+    }
+}
+
+impl Contract {
+    /// Account `used` tokens as claimed from the lockup and unlock it for the next claim.
+    /// Returns `true` if the lockup is fully claimed and removed,
+    /// the caller removes it from the account lockups.
+    pub fn finish_claim_internal(
+        &mut self,
+        index: LockupIndex,
+        mut lockup: Lockup,
+        account_id: &AccountId,
+        used: Balance,
+    ) -> bool {
+        if used > 0 {
+            events::LockupClaim {
+                index,
+                account_id,
+                amount: used.into(),
             }
+            .emit();
+        }
+
+        lockup.claimed_amount += used;
+        if lockup.remaining_amount() == 0 {
+            self.remove_lockup_internal(index, lockup);
+            true
+        } else {
+            lockup.is_claimed = false;
+            self.save_lockup_internal(index, &lockup);
+            false
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::{
+        test_utils::{accounts, VMContextBuilder},
+        testing_env,
+    };
+
+    #[test]
+    fn move_legacy_lockup() {
+        testing_env!(VMContextBuilder::new().build());
+        let mut contract = Contract::new(None, accounts(1), vec![accounts(2)]);
+        contract.legacy_lockups.insert(
+            &0,
+            &LockupV0 {
+                amount: 1_000,
+                unlock_on: 42,
+                is_claimed: true,
+            },
+        );
+        let mut lockup = contract.get_lockup_internal(0).unwrap();
+        assert_eq!(lockup.amount, 1_000);
+        assert_eq!(lockup.unlock_on, 42);
+        assert!(lockup.is_claimed);
+        assert!(lockup.creator.is_none());
+        assert!(!lockup.is_transferable);
+        assert_eq!(contract.next_index(), 1);
+
+        lockup.claimed_amount = 400;
+        contract.save_lockup_internal(0, &lockup);
+        assert!(contract.legacy_lockups.is_empty());
+        assert_eq!(contract.get_num_lockups().0, 1);
+        assert_eq!(
+            contract.get_lockup_internal(0).unwrap().remaining_amount(),
+            600
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use crate::*;
+use common::events::{self, RealisEvent};
+use near_sdk::{ext_contract, PromiseResult, Timestamp};
+
+#[derive(BorshDeserialize, BorshSerialize, Eq, PartialEq, PartialOrd, Hash, Clone)]
+pub struct Revocation {
+    /// Account that can revoke the lockup
+    pub revocable_by: AccountId,
+    /// Account to receive the unvested tokens
+    pub refund_account_id: AccountId,
+    /// The vesting stops since this moment
+    pub revoked_at: Option<Timestamp>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RevocationView {
+    pub revocable_by: AccountId,
+    pub refund_account_id: AccountId,
+    pub revoked_at: Option<U64>,
+}
+
+impl From<Revocation> for RevocationView {
+    fn from(revocation: Revocation) -> Self {
+        Self {
+            revocable_by: revocation.revocable_by,
+            refund_account_id: revocation.refund_account_id,
+            revoked_at: revocation.revoked_at.map(Into::into),
+        }
+    }
+}
+
+#[ext_contract(ext_self)]
+#[allow(dead_code)]
+trait SelfCallback {
+    fn lockup_revoke_callback(&mut self, index: LockupIndex, account_id: AccountId, amount: U128);
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Stop the vesting of the lockup and return the unvested tokens to the refund account.
+    /// The vested tokens stay claimable by `account_id`.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Caller must be the `revocable_by` account of the lockup.
+    /// * The lockup must have unvested tokens.
+    #[payable]
+    pub fn revoke(&mut self, index: LockupIndex, account_id: AccountId) -> Promise {
+        assert_one_yocto();
+        require!(
+            self.account_has_lockup(&account_id, index),
+            "No such lockup for this account"
+        );
+        let mut lockup = self
+            .get_lockup_internal(index)
+            .unwrap_or_else(|| env::panic_str("No such lockup for this account"));
+
+        let mut revocation = lockup
+            .revocation
+            .clone()
+            .filter(|r| r.revocable_by == env::predecessor_account_id())
+            .unwrap_or_else(|| env::panic_str("Not allowed to revoke the lockup"));
+        require!(revocation.revoked_at.is_none(), "Lockup already revoked");
+        let amount = lockup.amount - lockup.vested_amount();
+        require!(amount > 0, "Nothing to revoke");
+
+        revocation.revoked_at = Some(env::block_timestamp());
+        let refund_account_id = revocation.refund_account_id.clone();
+        lockup.revocation = Some(revocation);
+        self.save_lockup_internal(index, &lockup);
+
+        events::LockupRevoke {
+            index,
+            account_id: &account_id,
+            refund_account_id: &refund_account_id,
+            amount: amount.into(),
+        }
+        .emit();
+
+        ext_ft_core::ext(self.token_account_id.clone())
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .with_attached_deposit(ONE_YOCTO)
+            .ft_transfer(refund_account_id, amount.into(), None)
+            .then(
+                ext_self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_CLAIM_CALLBACK)
+                    .lockup_revoke_callback(index, account_id, amount.into()),
+            )
+    }
+}
+
+#[near_bindgen]
+impl SelfCallback for Contract {
+    /// Removes the lockup if nothing is left for the beneficiary,
+    /// or resumes the vesting if the refund transfer fails.
+    #[private]
+    fn lockup_revoke_callback(&mut self, index: LockupIndex, account_id: AccountId, amount: U128) {
+        let mut lockup = self
+            .get_lockup_internal(index)
+            .unwrap_or_else(|| env::panic_str("No such lockup for this account"));
+
+        match env::promise_result(0) {
+            PromiseResult::NotReady => env::abort(),
+            PromiseResult::Successful(_) => {
+                if lockup.remaining_amount() == 0 && !lockup.is_claimed {
+                    self.remove_lockup_internal(index, lockup);
+                    self.remove_account_lockup_internal(&account_id, index);
+                }
+            }
+            PromiseResult::Failed => {
+                if let Some(revocation) = lockup.revocation.as_mut() {
+                    revocation.revoked_at = None;
+                }
+                self.save_lockup_internal(index, &lockup);
+
+                events::LockupRevokeRollback {
+                    index,
+                    account_id: &account_id,
+                    amount,
+                }
+                .emit();
+            }
+        }
+    }
+}
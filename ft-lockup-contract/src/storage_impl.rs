@@ -0,0 +1,298 @@
+//! Storage deposits of the lockup creators.
+//!
+//! A creator pays for the bytes of each lockup from its storage balance,
+//! the bytes are returned to the balance when the lockup is removed.
+//! The lockup set of an account is paid by the account that caused its creation,
+//! either the creator of the first lockup or the sender of a transferred lockup,
+//! and is returned when the set is removed with the last lockup of the account.
+use crate::*;
+use near_contract_standards::storage_management::{
+    StorageBalance, StorageBalanceBounds, StorageManagement,
+};
+use near_sdk::{log, StorageUsage};
+
+/// Bytes of a storage deposit record with the longest account id, paid on the registration
+pub const STORAGE_DEPOSIT_BYTES: StorageUsage = 40 + 1 + 4 + 64 + 32;
+
+#[derive(BorshDeserialize, BorshSerialize, Eq, PartialEq, PartialOrd, Hash, Clone)]
+pub struct LockupStorage {
+    /// Account that paid for the storage and gets it back on the removal
+    pub payer_id: AccountId,
+    pub bytes: StorageUsage,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StorageDeposit {
+    pub total: Balance,
+    /// Paid for the registration and the lockups
+    pub used: Balance,
+}
+
+impl From<StorageDeposit> for StorageBalance {
+    fn from(deposit: StorageDeposit) -> Self {
+        Self {
+            total: deposit.total.into(),
+            available: (deposit.total - deposit.used).into(),
+        }
+    }
+}
+
+#[near_bindgen]
+impl StorageManagement for Contract {
+    #[payable]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let amount: Balance = env::attached_deposit();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let registration_only = registration_only.unwrap_or_default();
+        let min_balance = self.storage_balance_bounds().min.0;
+
+        let (mut deposit, refund) = match self.storage_deposits.get(&account_id) {
+            Some(deposit) if registration_only => {
+                log!("The account is already registered, refunding the deposit");
+                (deposit, amount)
+            }
+            Some(deposit) => (deposit, 0),
+            None => {
+                require!(
+                    amount >= min_balance,
+                    "The attached deposit is less than the minimum storage balance"
+                );
+                let deposit = StorageDeposit {
+                    total: 0,
+                    used: min_balance,
+                };
+                let refund = if registration_only {
+                    amount - min_balance
+                } else {
+                    0
+                };
+                (deposit, refund)
+            }
+        };
+
+        deposit.total += amount - refund;
+        self.storage_deposits.insert(&account_id, &deposit);
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+        deposit.into()
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut deposit = self.storage_deposits.get(&account_id).unwrap_or_else(|| {
+            env::panic_str(format!("The account {account_id} is not registered").as_str())
+        });
+        let available = deposit.total - deposit.used;
+        let amount = amount.map(|amount| amount.0).unwrap_or(available);
+        require!(
+            amount <= available,
+            "The amount is greater than the available storage balance"
+        );
+
+        deposit.total -= amount;
+        self.storage_deposits.insert(&account_id, &deposit);
+        if amount > 0 {
+            Promise::new(account_id).transfer(amount);
+        }
+        deposit.into()
+    }
+
+    /// The storage of the existing lockups can't be released, so `force` has no effect.
+    #[payable]
+    #[allow(unused_variables)]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        match self.storage_deposits.get(&account_id) {
+            Some(deposit) => {
+                require!(
+                    deposit.used <= self.storage_balance_bounds().min.0,
+                    "Can't unregister the account with the storage used by lockups"
+                );
+                self.storage_deposits.remove(&account_id);
+                Promise::new(account_id).transfer(deposit.total);
+                true
+            }
+            None => {
+                log!("The account {} is not registered", &account_id);
+                false
+            }
+        }
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: (Balance::from(STORAGE_DEPOSIT_BYTES) * env::storage_byte_cost()).into(),
+            max: None,
+        }
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_deposits.get(&account_id).map(Into::into)
+    }
+}
+
+impl Contract {
+    /// Charge `payer_id` for the bytes used since `initial_storage_usage` by the new lockup.
+    /// The lockup must be stored with the payer already, so its size doesn't change here.
+    pub fn charge_lockup_storage_internal(
+        &mut self,
+        index: LockupIndex,
+        payer_id: &AccountId,
+        initial_storage_usage: StorageUsage,
+    ) {
+        let bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+        self.charge_storage_internal(payer_id, bytes);
+
+        let mut lockup = self
+            .get_lockup_internal(index)
+            .unwrap_or_else(|| env::panic_str("No such lockup"));
+        lockup.storage = Some(LockupStorage {
+            payer_id: payer_id.clone(),
+            bytes,
+        });
+        self.save_lockup_internal(index, &lockup);
+    }
+
+    /// Remove the lockup and return its storage to the payer.
+    pub fn remove_lockup_internal(&mut self, index: LockupIndex, lockup: Lockup) {
+        self.lockups.remove(&index);
+        if !self.legacy_lockups.is_empty() {
+            self.legacy_lockups.remove(&index);
+        }
+        if let Some(storage) = lockup.storage {
+            self.refund_storage_internal(&storage);
+        }
+    }
+
+    pub fn charge_storage_internal(&mut self, payer_id: &AccountId, bytes: StorageUsage) {
+        let cost = Balance::from(bytes) * env::storage_byte_cost();
+        let mut deposit = self.storage_deposits.get(payer_id).unwrap_or_else(|| {
+            env::panic_str(format!("The account {payer_id} is not registered").as_str())
+        });
+        require!(
+            deposit.total - deposit.used >= cost,
+            format!("Not enough storage balance, {cost} yoctoNEAR is required")
+        );
+        deposit.used += cost;
+        self.storage_deposits.insert(payer_id, &deposit);
+    }
+
+    /// The payer could unregister only after all the storage it paid is returned.
+    pub fn refund_storage_internal(&mut self, storage: &LockupStorage) {
+        if let Some(mut deposit) = self.storage_deposits.get(&storage.payer_id) {
+            let cost = Balance::from(storage.bytes) * env::storage_byte_cost();
+            deposit.used = deposit.used.saturating_sub(cost);
+            self.storage_deposits.insert(&storage.payer_id, &deposit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::{
+        test_utils::{accounts, VMContextBuilder},
+        testing_env, ONE_NEAR,
+    };
+
+    #[test]
+    fn charge_and_refund_lockup_storage() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.attached_deposit(ONE_NEAR).build());
+        let mut contract = Contract::new(None, accounts(1), vec![accounts(2)]);
+        let min_balance = contract.storage_balance_bounds().min.0;
+        let balance = contract.storage_deposit(Some(accounts(2)), None);
+        assert_eq!(balance.total.0, ONE_NEAR);
+        assert_eq!(balance.available.0, ONE_NEAR - min_balance);
+
+        let index = contract.next_index();
+        let initial_storage_usage = env::storage_usage();
+        contract.lockups.insert(
+            &index,
+            &Lockup {
+                amount: 1_000,
+                unlock_on: 0,
+                is_claimed: false,
+                creator: Some(accounts(2)),
+                created_at: None,
+                memo: None,
+                schedule: None,
+                claimed_amount: 0,
+                revocation: None,
+                is_transferable: true,
+                storage: Some(LockupStorage {
+                    payer_id: accounts(2),
+                    bytes: 0,
+                }),
+                is_early_claimable: true,
+            },
+        );
+        contract.charge_lockup_storage_internal(index, &accounts(2), initial_storage_usage);
+        let lockup = contract.get_lockup_internal(index).unwrap();
+        let cost = Balance::from(lockup.storage.as_ref().unwrap().bytes) * env::storage_byte_cost();
+        assert!(cost > 0);
+        let balance = contract.storage_balance_of(accounts(2)).unwrap();
+        assert_eq!(balance.available.0, ONE_NEAR - min_balance - cost);
+
+        contract.remove_lockup_internal(index, lockup);
+        let balance = contract.storage_balance_of(accounts(2)).unwrap();
+        assert_eq!(balance.available.0, ONE_NEAR - min_balance);
+    }
+
+    #[test]
+    fn charge_and_refund_account_lockups_storage() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.attached_deposit(ONE_NEAR).build());
+        let mut contract = Contract::new(None, accounts(1), vec![accounts(2)]);
+        let min_balance = contract.storage_balance_bounds().min.0;
+        contract.storage_deposit(Some(accounts(2)), None);
+
+        // The set is paid once by the account that created it
+        for index in 0..2 {
+            contract.create_account_lockups_internal(&accounts(3), &accounts(2));
+            contract.add_account_lockup_internal(&accounts(3), index);
+        }
+        let storage = contract.account_lockups_storage.get(&accounts(3)).unwrap();
+        let cost = Balance::from(storage.bytes) * env::storage_byte_cost();
+        assert!(cost > 0);
+        let balance = contract.storage_balance_of(accounts(2)).unwrap();
+        assert_eq!(balance.available.0, ONE_NEAR - min_balance - cost);
+
+        // and returned with the last lockup of the account
+        contract.remove_account_lockup_internal(&accounts(3), 0);
+        let balance = contract.storage_balance_of(accounts(2)).unwrap();
+        assert_eq!(balance.available.0, ONE_NEAR - min_balance - cost);
+        contract.remove_account_lockup_internal(&accounts(3), 1);
+        assert!(contract.account_lockups_storage.get(&accounts(3)).is_none());
+        let balance = contract.storage_balance_of(accounts(2)).unwrap();
+        assert_eq!(balance.available.0, ONE_NEAR - min_balance);
+    }
+
+    #[test]
+    fn legacy_account_lockups_are_moved_by_contract() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.attached_deposit(ONE_NEAR).build());
+        let mut contract = Contract::new(None, accounts(1), vec![accounts(2)]);
+        let min_balance = contract.storage_balance_bounds().min.0;
+        contract.storage_deposit(Some(accounts(2)), None);
+        contract
+            .legacy_account_lockups
+            .insert(&accounts(3), &(0..10).collect());
+
+        contract.create_account_lockups_internal(&accounts(3), &accounts(2));
+        contract.add_account_lockup_internal(&accounts(3), 10);
+        assert!(contract.legacy_account_lockups.get(&accounts(3)).is_none());
+        assert_eq!(contract.get_account_num_lockups_internal(&accounts(3)), 11);
+        assert!(contract.account_lockups_storage.get(&accounts(3)).is_none());
+        let balance = contract.storage_balance_of(accounts(2)).unwrap();
+        assert_eq!(balance.available.0, ONE_NEAR - min_balance);
+    }
+}
@@ -0,0 +1,42 @@
+use crate::*;
+use common::events::{self, RealisEvent};
+
+#[near_bindgen]
+impl Contract {
+    /// Reassign the lockup of the caller to `receiver_id`.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * The lockup must belong to the caller and be transferable.
+    /// * The lockup must not be in the middle of a claim.
+    /// * Caller must have a storage balance to pay for the lockup set of the receiver
+    ///   if the receiver has no lockups.
+    #[payable]
+    pub fn transfer_lockup(&mut self, index: LockupIndex, receiver_id: AccountId) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        require!(account_id != receiver_id, "Can't transfer to self");
+        require!(
+            self.account_has_lockup(&account_id, index),
+            "No such lockup for this account"
+        );
+
+        let lockup = self
+            .get_lockup_internal(index)
+            .unwrap_or_else(|| env::panic_str("No such lockup for this account"));
+        require!(lockup.is_transferable, "The lockup is not transferable");
+        require!(!lockup.is_claimed, "Lockup already claimed");
+        require!(lockup.remaining_amount() > 0, "Nothing to transfer");
+
+        self.remove_account_lockup_internal(&account_id, index);
+        self.create_account_lockups_internal(&receiver_id, &account_id);
+        self.add_account_lockup_internal(&receiver_id, index);
+
+        events::LockupTransfer {
+            index,
+            old_account_id: &account_id,
+            new_account_id: &receiver_id,
+        }
+        .emit();
+    }
+}
@@ -1,6 +1,15 @@
 use crate::*;
+use common::{
+    events::{self, RealisEvent},
+    impl_staged_upgrade,
+    state_version::{migration_start, read_state, set_state_version, state_version, StateVersion},
+};
 use near_contract_standards::upgrade::Ownable;
 
+/// Version of the `Contract` layout
+pub const STATE_VERSION: StateVersion = 1;
+
+#[near_bindgen]
 impl Ownable for Contract {
     fn get_owner(&self) -> AccountId {
         self.owner_id.clone()
@@ -8,6 +17,11 @@ impl Ownable for Contract {
 
     fn set_owner(&mut self, owner: AccountId) {
         self.assert_owner();
+        events::OwnerChange {
+            old_owner_id: &self.owner_id,
+            new_owner_id: &owner,
+        }
+        .emit();
         self.owner_id = owner;
     }
 }
@@ -18,9 +32,59 @@ impl Contract {
         String::from("1.0.1")
     }
 
+    /// Migrate the state to the current layout.
+    /// Creators pay for the storage of the lockups since the state version 1,
+    /// so after the upgrade from the version 0 every whitelisted account,
+    /// the staking contract included, must make a `storage_deposit` to create lockups.
     #[private]
     #[init(ignore_state)]
     pub fn update() -> Self {
-        env::state_read().unwrap_or_else(|| env::panic_str("Not initialized"))
+        let contract = match migration_start(STATE_VERSION) {
+            0 => read_state::<ContractV0>().into(),
+            1 => read_state::<Contract>(),
+            _ => env::panic_str("Unknown state version"),
+        };
+        set_state_version(STATE_VERSION);
+        contract
+    }
+
+    pub fn get_state_version(&self) -> StateVersion {
+        state_version()
+    }
+}
+
+impl_staged_upgrade!(Contract);
+
+/// State layout before the versioning.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct ContractV0 {
+    pub owner_id: AccountId,
+    pub token_account_id: AccountId,
+    pub deposit_whitelist: UnorderedSet<AccountId>,
+    pub lockups: UnorderedMap<LockupIndex, LockupV0>,
+    pub account_lockups: LookupMap<AccountId, HashSet<LockupIndex>>,
+    pub index: LockupIndex,
+}
+
+/// Lockups and sets of the accounts are moved to the new layout lazily,
+/// see `save_lockup_internal` and `account_lockups` module.
+impl From<ContractV0> for Contract {
+    fn from(contract: ContractV0) -> Self {
+        Self {
+            owner_id: contract.owner_id,
+            token_account_id: contract.token_account_id,
+            deposit_whitelist: contract.deposit_whitelist,
+            lockups: UnorderedMap::new(StorageKey::LockupRecords),
+            legacy_account_lockups: contract.account_lockups,
+            index: contract.index,
+            staking_account_id: None,
+            account_lockups: LookupMap::new(StorageKey::AccountLockupSets),
+            storage_deposits: LookupMap::new(StorageKey::StorageDeposits),
+            early_claim_config: None,
+            lockup_limits: Default::default(),
+            unsent_penalties: 0,
+            account_lockups_storage: LookupMap::new(StorageKey::AccountLockupsStorage),
+            legacy_lockups: contract.lockups,
+        }
     }
 }
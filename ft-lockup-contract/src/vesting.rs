@@ -0,0 +1,149 @@
+//! Vesting schedules of lockups.
+//!
+//! Nothing is vested until `start + cliff`, then the tokens are released linearly
+//! until `start + duration`. With `tranche` the release happens by whole periods
+//! counted from `start`, the rest of the tokens vest at the end of the schedule.
+use crate::*;
+use near_sdk::{Balance, Timestamp};
+
+#[derive(BorshDeserialize, BorshSerialize, Eq, PartialEq, PartialOrd, Hash, Clone)]
+pub struct VestingSchedule {
+    pub start: Timestamp,
+    /// Time from `start` with nothing vested
+    pub cliff: Timestamp,
+    /// Time from `start` to vest all the tokens
+    pub duration: Timestamp,
+    /// Period of the release, continuous if `None`
+    pub tranche: Option<Timestamp>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VestingScheduleView {
+    /// Current block timestamp if not set
+    #[serde(default)]
+    pub start: Option<U64>,
+    /// No cliff if not set
+    #[serde(default)]
+    pub cliff: Option<U64>,
+    pub duration: U64,
+    #[serde(default)]
+    pub tranche: Option<U64>,
+}
+
+impl From<VestingSchedule> for VestingScheduleView {
+    fn from(schedule: VestingSchedule) -> Self {
+        Self {
+            start: Some(schedule.start.into()),
+            cliff: Some(schedule.cliff.into()),
+            duration: schedule.duration.into(),
+            tranche: schedule.tranche.map(Into::into),
+        }
+    }
+}
+
+impl From<VestingScheduleView> for VestingSchedule {
+    fn from(view: VestingScheduleView) -> Self {
+        let schedule = Self {
+            start: view.start.map(|s| s.0).unwrap_or_else(env::block_timestamp),
+            cliff: view.cliff.map(|c| c.0).unwrap_or_default(),
+            duration: view.duration.0,
+            tranche: view.tranche.map(|t| t.0),
+        };
+        schedule.assert_valid();
+        schedule
+    }
+}
+
+impl VestingSchedule {
+    pub fn assert_valid(&self) {
+        require!(self.duration > 0, "The vesting duration should be positive");
+        require!(
+            self.cliff <= self.duration,
+            "The cliff should not exceed the vesting duration"
+        );
+        if let Some(tranche) = self.tranche {
+            require!(
+                tranche > 0 && tranche <= self.duration,
+                "The tranche should be positive and not exceed the vesting duration"
+            );
+        }
+        self.end();
+    }
+
+    /// Moment when all the tokens are vested
+    pub fn end(&self) -> Timestamp {
+        self.start
+            .checked_add(self.duration)
+            .unwrap_or_else(|| env::panic_str("Add will overflow"))
+    }
+
+    /// Part of `amount` vested at `timestamp`
+    pub fn vested_amount(&self, amount: Balance, timestamp: Timestamp) -> Balance {
+        let elapsed = timestamp.saturating_sub(self.start);
+        if elapsed < self.cliff {
+            return 0;
+        }
+        if elapsed >= self.duration {
+            return amount;
+        }
+        let elapsed = match self.tranche {
+            Some(tranche) => elapsed / tranche * tranche,
+            None => elapsed,
+        } as Balance;
+        let duration = self.duration as Balance;
+        // `amount * elapsed / duration` without the overflow
+        amount / duration * elapsed + amount % duration * elapsed / duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AMOUNT: Balance = 3_000_000_000 * 10_u128.pow(12);
+
+    fn schedule(tranche: Option<Timestamp>) -> VestingSchedule {
+        VestingSchedule {
+            start: 1_000,
+            cliff: 100,
+            duration: 400,
+            tranche,
+        }
+    }
+
+    #[test]
+    fn linear() {
+        let schedule = schedule(None);
+        assert_eq!(schedule.vested_amount(AMOUNT, 0), 0);
+        assert_eq!(schedule.vested_amount(AMOUNT, 1_099), 0);
+        assert_eq!(schedule.vested_amount(AMOUNT, 1_100), AMOUNT / 4);
+        assert_eq!(schedule.vested_amount(AMOUNT, 1_200), AMOUNT / 2);
+        assert_eq!(schedule.vested_amount(AMOUNT, 1_400), AMOUNT);
+        assert_eq!(schedule.vested_amount(AMOUNT, u64::MAX), AMOUNT);
+    }
+
+    #[test]
+    fn tranches() {
+        let schedule = schedule(Some(150));
+        assert_eq!(schedule.vested_amount(AMOUNT, 1_100), 0);
+        assert_eq!(schedule.vested_amount(AMOUNT, 1_150), AMOUNT * 150 / 400);
+        assert_eq!(schedule.vested_amount(AMOUNT, 1_299), AMOUNT * 150 / 400);
+        assert_eq!(schedule.vested_amount(AMOUNT, 1_300), AMOUNT * 300 / 400);
+        assert_eq!(schedule.vested_amount(AMOUNT, 1_399), AMOUNT * 300 / 400);
+        assert_eq!(schedule.vested_amount(AMOUNT, 1_400), AMOUNT);
+    }
+
+    #[test]
+    fn no_overflow() {
+        let schedule = VestingSchedule {
+            start: 0,
+            cliff: 0,
+            duration: u64::MAX,
+            tranche: None,
+        };
+        let vested = schedule.vested_amount(Balance::MAX, u64::MAX / 2);
+        assert!(vested <= Balance::MAX / 2);
+        assert!(vested >= Balance::MAX / 2 - Balance::MAX / u64::MAX as Balance);
+    }
+}
@@ -1,5 +1,7 @@
 use crate::*;
+use revoke::RevocationView;
 use std::collections::HashMap;
+use vesting::VestingScheduleView;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
@@ -7,43 +9,120 @@ pub struct LockupView {
     amount: U128,
     unlock_on: U64,
     is_claimed: bool,
+    creator: Option<AccountId>,
+    created_at: Option<U64>,
+    memo: Option<String>,
+    schedule: Option<VestingScheduleView>,
+    /// Tokens vested so far including the claimed ones
+    vested: U128,
+    claimed: U128,
+    /// Tokens left in the lockup
+    remaining: U128,
+    revocation: Option<RevocationView>,
+    is_transferable: bool,
+    is_early_claimable: bool,
 }
 
 impl From<Lockup> for LockupView {
     fn from(lockup: Lockup) -> Self {
         Self {
+            vested: lockup.vested_amount().into(),
+            claimed: lockup.claimed_amount.into(),
+            remaining: lockup.remaining_amount().into(),
             amount: lockup.amount.into(),
             unlock_on: lockup.unlock_on.into(),
             is_claimed: lockup.is_claimed,
+            creator: lockup.creator,
+            created_at: lockup.created_at.map(Into::into),
+            memo: lockup.memo,
+            schedule: lockup.schedule.map(Into::into),
+            revocation: lockup.revocation.map(Into::into),
+            is_transferable: lockup.is_transferable,
+            is_early_claimable: lockup.is_early_claimable,
         }
     }
 }
 
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LockupFilter {
+    #[serde(default)]
+    pub account_id: Option<AccountId>,
+    /// Only lockups with tokens to claim right now
+    #[serde(default)]
+    pub claimable: Option<bool>,
+    /// Inclusive lower bound of `unlock_on`
+    #[serde(default)]
+    pub unlock_from: Option<U64>,
+    /// Exclusive upper bound of `unlock_on`
+    #[serde(default)]
+    pub unlock_to: Option<U64>,
+}
+
+impl LockupFilter {
+    /// Account is checked by the caller
+    fn matches(&self, lockup: &Lockup) -> bool {
+        let is_claimable = !lockup.is_claimed && lockup.claimable_amount() > 0;
+        self.claimable
+            .map(|claimable| claimable == is_claimable)
+            .unwrap_or(true)
+            && self
+                .unlock_from
+                .map(|from| lockup.unlock_on >= from.0)
+                .unwrap_or(true)
+            && self
+                .unlock_to
+                .map(|to| lockup.unlock_on < to.0)
+                .unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct IndexedLockupView {
+    pub index: LockupIndex,
+    #[serde(flatten)]
+    pub lockup: LockupView,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LockupsPage {
+    pub lockups: Vec<IndexedLockupView>,
+    /// The last scanned index to continue after, the last page if `None`
+    pub next_cursor: Option<LockupIndex>,
+}
+
 #[near_bindgen]
 impl Contract {
     pub fn get_token_account_id(&self) -> AccountId {
         self.token_account_id.clone()
     }
 
+    pub fn get_staking_account_id(&self) -> Option<AccountId> {
+        self.staking_account_id.clone()
+    }
+
+    pub fn get_lockup_limits(&self) -> ft_token_receiver::LockupLimits {
+        self.lockup_limits.clone()
+    }
+
     pub fn get_deposit_whitelist(&self) -> Vec<AccountId> {
         self.deposit_whitelist.to_vec()
     }
 
     pub fn get_num_lockups(&self) -> U64 {
-        self.lockups.len().into()
+        (self.lockups.len() + self.legacy_lockups.len()).into()
     }
 
     pub fn get_account_num_lockups(&self, account_id: AccountId) -> u32 {
-        self.account_lockups
-            .get(&account_id)
-            .unwrap_or_default()
-            .len()
+        self.get_account_num_lockups_internal(&account_id)
             .try_into()
             .unwrap()
     }
 
     pub fn get_lockup(&self, index: LockupIndex) -> Option<LockupView> {
-        self.lockups.get(&index).map(|lockup| lockup.into())
+        self.get_lockup_internal(index).map(Into::into)
     }
 
     pub fn get_lockups(&self, indexes: Vec<LockupIndex>) -> HashMap<LockupIndex, LockupView> {
@@ -53,36 +132,83 @@ impl Contract {
             .collect()
     }
 
+    /// Lockups with indexes greater than `cursor` matching the filter, in ascending order.
+    /// Continue with `next_cursor` of the page until it is `None`,
+    /// lockups removed between the pages don't move the rest of them.
+    /// A page may have less than `limit` lockups if `MAX_PAGE_SCAN` indexes are scanned.
+    /// `limit` is at least 1, so every page moves the cursor.
+    pub fn get_lockups_page(
+        &self,
+        cursor: Option<LockupIndex>,
+        limit: Option<u32>,
+        filter: Option<LockupFilter>,
+    ) -> LockupsPage {
+        let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).max(1) as usize;
+        let filter = filter.unwrap_or_default();
+        let indexes: Box<dyn Iterator<Item = LockupIndex>> = match &filter.account_id {
+            Some(account_id) => self.get_account_lockup_indexes(account_id, cursor),
+            None if self.get_num_lockups().0 == 0 => Box::new(std::iter::empty()),
+            None => match cursor {
+                Some(cursor) => Box::new((cursor..=self.index).skip(1)),
+                None => Box::new(0..=self.index),
+            },
+        };
+
+        let mut lockups = vec![];
+        let mut last_scanned = None;
+        for (scanned, index) in indexes.enumerate() {
+            if lockups.len() == limit || scanned == MAX_PAGE_SCAN {
+                return LockupsPage {
+                    lockups,
+                    next_cursor: last_scanned,
+                };
+            }
+            if let Some(lockup) = self
+                .get_lockup_internal(index)
+                .filter(|lockup| filter.matches(lockup))
+            {
+                lockups.push(IndexedLockupView {
+                    index,
+                    lockup: lockup.into(),
+                });
+            }
+            last_scanned = Some(index);
+        }
+        LockupsPage {
+            lockups,
+            next_cursor: None,
+        }
+    }
+
+    /// Up to `limit` lockups with indexes from `from_index`, all of them by default.
+    /// Gaps of the removed lockups are skipped without the `MAX_PAGE_SCAN` bound,
+    /// use `get_lockups_page` to page through a large number of lockups.
     pub fn get_lockups_paged(
         &self,
         from_index: Option<LockupIndex>,
         limit: Option<LockupIndex>,
     ) -> HashMap<LockupIndex, LockupView> {
-        let from_index = from_index.unwrap_or(0);
-        let limit = limit.unwrap_or(self.get_num_lockups().0 as u32);
-        (from_index..std::cmp::min(self.get_num_lockups().0 as u32, limit))
+        if self.get_num_lockups().0 == 0 {
+            return HashMap::new();
+        }
+        (from_index.unwrap_or_default()..=self.index)
             .filter_map(|index| self.get_lockup(index).map(|lockup| (index, lockup)))
+            .take(limit.unwrap_or(LockupIndex::MAX) as usize)
             .collect()
     }
 
+    /// Lockups of `account_id` skipping the first `from_index` of them in the order of indexes,
+    /// up to `limit` lockups, all of them by default.
     pub fn get_account_lockups(
         &self,
         account_id: AccountId,
         from_index: Option<LockupIndex>,
         limit: Option<LockupIndex>,
     ) -> HashMap<LockupIndex, LockupView> {
-        self.account_lockups
-            .get(&account_id)
-            .unwrap_or_default()
-            .into_iter()
+        self.get_account_lockup_indexes(&account_id, None)
             .skip(from_index.unwrap_or_default() as usize)
             .take(limit.unwrap_or(LockupIndex::MAX) as usize)
-            .map(|lockup_index| {
-                (
-                    lockup_index,
-                    self.lockups.get(&lockup_index).unwrap().into(),
-                )
-            })
+            .filter_map(|index| self.get_lockup(index).map(|lockup| (index, lockup)))
             .collect()
     }
 }
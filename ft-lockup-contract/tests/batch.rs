@@ -0,0 +1,138 @@
+use near_sdk::serde_json;
+use test_utils::{lockup, utils::*, SandboxEnvironment};
+use workspaces::result::ExecutionSuccess;
+
+const BATCH_SIZE: usize = 20;
+/// Same as in the contract
+const MAX_BATCH_SIZE: usize = 50;
+const GAS_FOR_BATCH_LOCKUP: u64 = 5_000_000_000_000;
+
+/// Lockups of `amount` split between the owner and the backend
+async fn transfer_batch(
+    sandbox: &SandboxEnvironment,
+    size: usize,
+    amount: u128,
+) -> anyhow::Result<ExecutionSuccess> {
+    let batch: Vec<_> = (0..size)
+        .map(|i| {
+            let account_id = if i % 2 == 0 {
+                sandbox.owner.id()
+            } else {
+                sandbox.backend.id()
+            };
+            serde_json::json!({
+                "account_id": account_id,
+                "amount": amount.to_string(),
+                "duration": WEEK,
+            })
+        })
+        .collect();
+    lockup::create_lockup(
+        sandbox,
+        amount * size as u128,
+        serde_json::json!({ "V1": { "Batch": batch } }),
+    )
+    .await
+}
+
+fn num_created(outcome: &ExecutionSuccess) -> usize {
+    outcome
+        .logs()
+        .iter()
+        .filter(|log| log.contains("\"event\":\"lockup_create\""))
+        .count()
+}
+
+/// Gas burnt by the receipts of the lockup contract only
+fn lockup_gas_burnt(sandbox: &SandboxEnvironment, outcome: &ExecutionSuccess) -> u64 {
+    outcome
+        .receipt_outcomes()
+        .iter()
+        .filter(|receipt| &receipt.executor_id == sandbox.lockup.id())
+        .map(|receipt| receipt.gas_burnt)
+        .sum()
+}
+
+#[tokio::test]
+async fn create_batch() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+
+    let outcome = transfer_batch(&sandbox, BATCH_SIZE, 10 * LIS).await?;
+    assert!(outcome.receipt_failures().is_empty());
+    assert_eq!(BATCH_SIZE, num_created(&outcome));
+
+    for account_id in [sandbox.owner.id(), sandbox.backend.id()] {
+        let lockups = lockup::get_account_lockups(&sandbox.lockup, account_id).await?;
+        assert_eq!(BATCH_SIZE / 2, lockups.len());
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn batch_lockup_gas() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+
+    // The first lockups of the accounts create their sets
+    transfer_batch(&sandbox, 2, LIS).await?;
+    let single_gas = lockup_gas_burnt(&sandbox, &transfer_batch(&sandbox, 1, LIS).await?);
+    let batch_gas = lockup_gas_burnt(
+        &sandbox,
+        &transfer_batch(&sandbox, BATCH_SIZE + 1, LIS).await?,
+    );
+    let lockup_gas = (batch_gas - single_gas) / BATCH_SIZE as u64;
+    println!("Gas burnt per lockup of a batch: {}", lockup_gas);
+    assert!(
+        lockup_gas <= GAS_FOR_BATCH_LOCKUP,
+        "Lockup of a batch burns {} gas, more than {}",
+        lockup_gas,
+        GAS_FOR_BATCH_LOCKUP
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn create_max_batch() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+
+    let outcome = transfer_batch(&sandbox, MAX_BATCH_SIZE, LIS).await?;
+    assert!(outcome.receipt_failures().is_empty());
+    assert_eq!(MAX_BATCH_SIZE, num_created(&outcome));
+
+    let outcome = transfer_batch(&sandbox, MAX_BATCH_SIZE + 1, LIS).await?;
+    let failure = outcome.receipt_failures()[0];
+    assert!(format!("{:?}", failure).contains("The batch size should be from 1 to"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn create_batch_with_wrong_total() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+
+    let outcome = lockup::create_lockup(
+        &sandbox,
+        3 * LIS,
+        serde_json::json!({
+            "V1": {
+                "Batch": [
+                    { "account_id": sandbox.owner.id(), "amount": LIS.to_string(), "duration": WEEK },
+                    { "account_id": sandbox.backend.id(), "amount": LIS.to_string(), "duration": WEEK },
+                ],
+            },
+        }),
+    )
+    .await?;
+
+    let failure = outcome.receipt_failures()[0];
+    assert!(format!("{:?}", failure).contains("doesn't match the transferred amount"));
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id()).await?;
+    assert!(lockups.is_empty());
+
+    Ok(())
+}
@@ -62,7 +62,7 @@ async fn claim() -> anyhow::Result<()> {
         3_000_000_000_u128 * LIS - amount,
         token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await?
     );
-    sandbox
+    let outcome = sandbox
         .owner
         .call(sandbox.lockup.id(), "claim")
         .deposit(YOCTO)
@@ -77,6 +77,12 @@ async fn claim() -> anyhow::Result<()> {
         3_000_000_000_u128 * LIS,
         token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await?
     );
+    assert!(outcome.logs().contains(&format!(
+        r#"EVENT_JSON:{{"data":[{{"account_id":"{}","amount":"{}","index":{}}}],"event":"lockup_claim","standard":"realis","version":"1.0.0"}}"#,
+        sandbox.owner.id(),
+        amount,
+        lockup_index
+    ).as_str()));
 
     Ok(())
 }
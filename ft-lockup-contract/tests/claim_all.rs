@@ -0,0 +1,108 @@
+use near_sdk::serde_json;
+use test_utils::{lockup, token, utils::*, SandboxEnvironment};
+
+#[tokio::test]
+async fn claim_all() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+
+    for _ in 0..3 {
+        lockup::create_lockup(
+            &sandbox,
+            amount,
+            serde_json::json!({
+                "duration": SECOND,
+                "account_id": sandbox.owner.id(),
+            }),
+        )
+        .await?;
+    }
+    // Not expired lockup stays
+    lockup::create_lockup(
+        &sandbox,
+        amount,
+        serde_json::json!({
+            "duration": WEEK,
+            "account_id": sandbox.owner.id(),
+        }),
+    )
+    .await?;
+
+    worker.fast_forward(100).await?;
+
+    let balance = token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await?;
+    let outcome = sandbox
+        .owner
+        .call(sandbox.lockup.id(), "claim_all")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({}))
+        .transact()
+        .await?
+        .into_result()?;
+
+    assert_eq!(
+        balance + 3 * amount,
+        token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await?
+    );
+    assert_eq!(
+        3,
+        outcome
+            .logs()
+            .iter()
+            .filter(|log| log.contains("\"event\":\"lockup_claim\""))
+            .count()
+    );
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id()).await?;
+    assert_eq!(1, lockups.len());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn claim_all_error_transfer_tokens() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+    let user = sandbox
+        .owner
+        .create_subaccount("user")
+        .initial_balance(10 * NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    for _ in 0..2 {
+        lockup::create_lockup(
+            &sandbox,
+            amount,
+            serde_json::json!({
+                "duration": SECOND,
+                "account_id": user.id(),
+            }),
+        )
+        .await?;
+    }
+
+    worker.fast_forward(100).await?;
+
+    // `user` do not have storage deposit on token contract
+    // so the transfer fails and lockups are unlocked back
+    user.call(sandbox.lockup.id(), "claim_all")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({}))
+        .transact()
+        .await?
+        .into_result()?;
+    assert_eq!(0, token::ft_balance_of(&sandbox.token, user.id()).await?);
+
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, user.id()).await?;
+    assert_eq!(2, lockups.len());
+    assert!(lockups
+        .values()
+        .all(|lockup| lockup["is_claimed"] == false && lockup["claimed"] == "0"));
+
+    Ok(())
+}
@@ -0,0 +1,261 @@
+use near_sdk::{json_types::U128, serde_json};
+use test_utils::{lockup, token, utils::*, SandboxEnvironment};
+use workspaces::{result::ExecutionFinalResult, AccountId};
+
+async fn set_early_claim_config(sandbox: &SandboxEnvironment) -> anyhow::Result<()> {
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "set_early_claim_config")
+        .deposit(YOCTO)
+        .args_json(serde_json::json!({
+            "config": {
+                "min_penalty_bps": 1_000,
+                "max_penalty_bps": 5_000,
+                "exponent": 1,
+                "penalty_receiver_id": sandbox.backend.id(),
+            },
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    Ok(())
+}
+
+/// Penalties are added to the staking pool
+async fn set_add_to_pool_config(sandbox: &SandboxEnvironment) -> anyhow::Result<()> {
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "set_early_claim_config")
+        .deposit(YOCTO)
+        .args_json(serde_json::json!({
+            "config": {
+                "min_penalty_bps": 1_000,
+                "max_penalty_bps": 5_000,
+                "exponent": 1,
+                "penalty_receiver_id": sandbox.staking.id(),
+                "penalty_msg": "\"AddToPool\"",
+            },
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    Ok(())
+}
+
+/// Create a week lockup for the owner.
+async fn create_week_lockup(sandbox: &SandboxEnvironment, amount: u128) -> anyhow::Result<u32> {
+    lockup::create_lockup(
+        sandbox,
+        amount,
+        serde_json::json!({
+            "duration": WEEK,
+            "account_id": sandbox.owner.id(),
+        }),
+    )
+    .await?;
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id()).await?;
+    Ok(*lockups.keys().next().expect("No lockups created!"))
+}
+
+async fn call_claim_early(
+    sandbox: &SandboxEnvironment,
+    index: u32,
+) -> anyhow::Result<ExecutionFinalResult> {
+    Ok(sandbox
+        .owner
+        .call(sandbox.lockup.id(), "claim_early")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({ "index": index }))
+        .transact()
+        .await?)
+}
+
+async fn get_unsent_penalties(sandbox: &SandboxEnvironment) -> anyhow::Result<u128> {
+    let penalties: U128 = sandbox
+        .lockup
+        .view("get_unsent_penalties", Vec::new())
+        .await?
+        .json()?;
+    Ok(penalties.0)
+}
+
+async fn balances(
+    sandbox: &SandboxEnvironment,
+    account_ids: [&AccountId; 3],
+) -> anyhow::Result<Vec<u128>> {
+    let mut balances = Vec::new();
+    for account_id in account_ids {
+        balances.push(token::ft_balance_of(&sandbox.token, account_id).await?);
+    }
+    Ok(balances)
+}
+
+#[tokio::test]
+async fn claim_early() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+    set_early_claim_config(&sandbox).await?;
+
+    lockup::create_lockup(
+        &sandbox,
+        amount,
+        serde_json::json!({
+            "duration": WEEK,
+            "account_id": sandbox.owner.id(),
+        }),
+    )
+    .await?;
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id()).await?;
+    let lockup_index = lockups.keys().next().expect("No lockups created!");
+
+    let balance = token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await?;
+    let penalty_balance = token::ft_balance_of(&sandbox.token, sandbox.backend.id()).await?;
+    let outcome = sandbox
+        .owner
+        .call(sandbox.lockup.id(), "claim_early")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({ "index": lockup_index }))
+        .transact()
+        .await?
+        .into_result()?;
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"lockup_claim_early\"")));
+
+    // The whole week is left, so the penalty is close to the max
+    let claimed = token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await? - balance;
+    let penalty =
+        token::ft_balance_of(&sandbox.token, sandbox.backend.id()).await? - penalty_balance;
+    assert_eq!(amount, claimed + penalty);
+    assert!(penalty > amount * 45 / 100 && penalty <= amount / 2);
+
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id()).await?;
+    assert!(lockups.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn claim_early_opted_out_lockup() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    set_early_claim_config(&sandbox).await?;
+
+    lockup::create_lockup(
+        &sandbox,
+        100 * LIS,
+        serde_json::json!({
+            "duration": WEEK,
+            "account_id": sandbox.owner.id(),
+            "early_claimable": false,
+        }),
+    )
+    .await?;
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id()).await?;
+    let lockup_index = lockups.keys().next().expect("No lockups created!");
+
+    let result = sandbox
+        .owner
+        .call(sandbox.lockup.id(), "claim_early")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({ "index": lockup_index }))
+        .transact()
+        .await?
+        .into_result();
+
+    assert!(result.is_err());
+    let outcome = result.unwrap_err();
+    let failure = outcome.receipt_failures()[0];
+    assert!(format!("{:?}", failure).contains("The lockup can't be claimed early"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn claim_early_with_penalty_added_to_pool() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+    set_add_to_pool_config(&sandbox).await?;
+
+    let index = create_week_lockup(&sandbox, amount).await?;
+    let accounts = [
+        sandbox.owner.id(),
+        sandbox.staking.id(),
+        sandbox.lockup.id(),
+    ];
+    let before = balances(&sandbox, accounts).await?;
+    let outcome = call_claim_early(&sandbox, index).await?;
+    assert!(outcome.receipt_failures().is_empty());
+    let after = balances(&sandbox, accounts).await?;
+
+    let claimed = after[0] - before[0];
+    let penalty = after[1] - before[1];
+    assert_eq!(amount, claimed + penalty);
+    assert!(penalty > amount * 45 / 100 && penalty <= amount / 2);
+    assert_eq!(before[2], after[2]);
+    assert_eq!(0, get_unsent_penalties(&sandbox).await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn claim_early_with_refunded_penalty() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+    set_add_to_pool_config(&sandbox).await?;
+
+    // Staking refunds the deposits in the emergency mode
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "set_emergency_mode")
+        .deposit(YOCTO)
+        .args_json(serde_json::json!({ "enabled": true }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let index = create_week_lockup(&sandbox, amount).await?;
+    let accounts = [
+        sandbox.owner.id(),
+        sandbox.staking.id(),
+        sandbox.lockup.id(),
+    ];
+    let before = balances(&sandbox, accounts).await?;
+    call_claim_early(&sandbox, index).await?;
+    let after = balances(&sandbox, accounts).await?;
+
+    // The penalty is refunded to the lockup contract and recorded
+    let claimed = after[0] - before[0];
+    let penalty = amount - claimed;
+    assert_eq!(before[2] - claimed, after[2]);
+    assert_eq!(before[1], after[1]);
+    assert!(penalty > 0);
+    assert_eq!(penalty, get_unsent_penalties(&sandbox).await?);
+
+    let balance = token::ft_balance_of(&sandbox.token, sandbox.backend.id()).await?;
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "withdraw_unsent_penalties")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({ "account_id": sandbox.backend.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+    assert_eq!(
+        balance + penalty,
+        token::ft_balance_of(&sandbox.token, sandbox.backend.id()).await?
+    );
+    assert_eq!(0, get_unsent_penalties(&sandbox).await?);
+
+    Ok(())
+}
@@ -1,3 +1,5 @@
+use near_sdk::serde_json;
+use std::collections::HashMap;
 use test_utils::{token, utils::*, SandboxEnvironment};
 
 #[tokio::test]
@@ -100,3 +102,69 @@ async fn create_lock_with_0_amount() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn create_lock_with_memo() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+
+    // Only staking contract can create lockups
+    // so transfer funds to staking contract
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer")
+        .deposit(YOCTO)
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.staking.id(),
+            "amount": amount.to_string(),
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let outcome = sandbox
+        .staking
+        .as_account()
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.lockup.id(),
+            "amount": amount.to_string(),
+            "msg": serde_json::json!({
+                "duration": HOUR,
+                "account_id": sandbox.owner.id(),
+                "memo": "Team allocation",
+            }).to_string()
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"lockup_create\"")
+            && log.contains("\"memo\":\"Team allocation\"")));
+
+    let lockups: HashMap<u32, serde_json::Value> = sandbox
+        .owner
+        .view(
+            sandbox.lockup.id(),
+            "get_account_lockups",
+            serde_json::json!({
+                "account_id": sandbox.owner.id(),
+            })
+            .to_string()
+            .into_bytes(),
+        )
+        .await?
+        .json()?;
+    let lockup = lockups.values().next().expect("No lockups created!");
+
+    assert_eq!(lockup["creator"], STAKING_CONTRACT_ACCOUNT);
+    assert_eq!(lockup["memo"], "Team allocation");
+    assert!(lockup["created_at"].is_string());
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use near_sdk::serde_json;
+use test_utils::{lockup, utils::*, SandboxEnvironment};
+use workspaces::result::ExecutionSuccess;
+
+/// Number of lockups the account gets before the second measurement
+const NUM_LOCKUPS: usize = 200;
+
+/// Gas burnt by the receipts of the lockup contract only
+fn lockup_gas_burnt(sandbox: &SandboxEnvironment, outcome: &ExecutionSuccess) -> u64 {
+    outcome
+        .receipt_outcomes()
+        .iter()
+        .filter(|receipt| &receipt.executor_id == sandbox.lockup.id())
+        .map(|receipt| receipt.gas_burnt)
+        .sum()
+}
+
+async fn claim(sandbox: &SandboxEnvironment, index: u32) -> anyhow::Result<ExecutionSuccess> {
+    Ok(sandbox
+        .owner
+        .call(sandbox.lockup.id(), "claim")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({ "index": index }))
+        .transact()
+        .await?
+        .into_result()?)
+}
+
+fn assert_flat(name: &str, before: u64, after: u64) {
+    assert!(
+        after <= before + before / 10,
+        "{} gas grows with the number of lockups: {} -> {}",
+        name,
+        before,
+        after
+    );
+}
+
+#[tokio::test]
+async fn gas_is_flat_for_many_lockups() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = LIS;
+    let msg = serde_json::json!({
+        "duration": SECOND,
+        "account_id": sandbox.owner.id(),
+    });
+
+    lockup::create_lockup(&sandbox, amount, msg.clone()).await?;
+    let outcome = lockup::create_lockup(&sandbox, amount, msg.clone()).await?;
+    let create_gas = lockup_gas_burnt(&sandbox, &outcome);
+
+    worker.fast_forward(100).await?;
+    let mut indexes: Vec<u32> = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id())
+        .await?
+        .into_keys()
+        .collect();
+    indexes.sort_unstable();
+    let outcome = claim(&sandbox, indexes[0]).await?;
+    let claim_gas = lockup_gas_burnt(&sandbox, &outcome);
+
+    let mut outcome = None;
+    for _ in 0..NUM_LOCKUPS {
+        outcome = Some(lockup::create_lockup(&sandbox, amount, msg.clone()).await?);
+    }
+    let create_many_gas = lockup_gas_burnt(&sandbox, &outcome.unwrap());
+    let num_lockups: u32 = sandbox
+        .lockup
+        .view(
+            "get_account_num_lockups",
+            serde_json::json!({ "account_id": sandbox.owner.id() })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(NUM_LOCKUPS as u32 + 1, num_lockups);
+
+    worker.fast_forward(100).await?;
+    let outcome = claim(&sandbox, indexes[1]).await?;
+    let claim_many_gas = lockup_gas_burnt(&sandbox, &outcome);
+
+    assert_flat("Create", create_gas, create_many_gas);
+    assert_flat("Claim", claim_gas, claim_many_gas);
+
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use near_sdk::{json_types::U64, serde_json};
+use test_utils::{lockup, token, utils::*, SandboxEnvironment};
+
+#[tokio::test]
+async fn create_lockup_with_versioned_message() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+
+    lockup::create_lockup(
+        &sandbox,
+        100 * LIS,
+        serde_json::json!({
+            "V1": {
+                "Lockup": {
+                    "account_id": sandbox.owner.id(),
+                    "duration": WEEK,
+                    "memo": "Versioned",
+                },
+            },
+        }),
+    )
+    .await?;
+
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id()).await?;
+    assert_eq!(1, lockups.len());
+    let lockup = lockups.values().next().unwrap();
+    assert_eq!(lockup["memo"], "Versioned");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn create_lockup_with_invalid_message() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+
+    let outcome = lockup::create_lockup(
+        &sandbox,
+        100 * LIS,
+        serde_json::json!({
+            "V1": {
+                "Lockup": {
+                    "account_id": sandbox.owner.id(),
+                },
+            },
+        }),
+    )
+    .await?;
+
+    let failure = outcome.receipt_failures()[0];
+    assert!(format!("{:?}", failure).contains("Invalid lockup message: missing field `duration`"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn create_lockup_out_of_limits() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "set_lockup_limits")
+        .deposit(YOCTO)
+        .args_json(serde_json::json!({
+            "limits": {
+                "min_duration": DAY,
+                "max_duration": U64(2 * WEEK.0),
+                "min_amount": (10 * LIS).to_string(),
+            },
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    for (amount, duration) in [(LIS, WEEK), (10 * LIS, SECOND), (10 * LIS, U64(3 * WEEK.0))] {
+        let balance = token::ft_balance_of(&sandbox.token, sandbox.staking.id()).await?;
+        let outcome = lockup::create_lockup(
+            &sandbox,
+            amount,
+            serde_json::json!({
+                "duration": duration,
+                "account_id": sandbox.owner.id(),
+            }),
+        )
+        .await?;
+        assert!(outcome
+            .logs()
+            .iter()
+            .any(|log| log.starts_with("Refund the lockup")));
+        // The tokens are returned to the creator
+        assert_eq!(
+            balance + amount,
+            token::ft_balance_of(&sandbox.token, sandbox.staking.id()).await?
+        );
+    }
+
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id()).await?;
+    assert!(lockups.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,222 @@
+use near_sdk::serde_json;
+use std::collections::HashMap;
+use test_utils::{lockup, utils::*, SandboxEnvironment};
+
+async fn get_lockups_page(
+    contract: &workspaces::Contract,
+    args: serde_json::Value,
+) -> anyhow::Result<serde_json::Value> {
+    Ok(contract
+        .view("get_lockups_page", args.to_string().into_bytes())
+        .await?
+        .json()?)
+}
+
+#[tokio::test]
+async fn paginate_with_gaps() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+
+    for _ in 0..3 {
+        lockup::create_lockup(
+            &sandbox,
+            amount,
+            serde_json::json!({
+                "duration": SECOND,
+                "account_id": sandbox.owner.id(),
+            }),
+        )
+        .await?;
+    }
+    lockup::create_lockup(
+        &sandbox,
+        amount,
+        serde_json::json!({
+            "duration": WEEK,
+            "account_id": sandbox.owner.id(),
+        }),
+    )
+    .await?;
+
+    worker.fast_forward(100).await?;
+
+    // Claim one of the first lockups to make a gap
+    let mut indexes: Vec<u32> = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id())
+        .await?
+        .into_keys()
+        .collect();
+    indexes.sort_unstable();
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "claim")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({ "index": indexes[1] }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let mut cursor = serde_json::Value::Null;
+    let mut collected = vec![];
+    loop {
+        let page = get_lockups_page(
+            &sandbox.lockup,
+            serde_json::json!({ "cursor": cursor, "limit": 1 }),
+        )
+        .await?;
+        collected.extend(
+            page["lockups"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|lockup| lockup["index"].as_u64().unwrap() as u32),
+        );
+        cursor = page["next_cursor"].clone();
+        if cursor.is_null() {
+            break;
+        }
+    }
+    assert_eq!(collected, vec![indexes[0], indexes[2], indexes[3]]);
+
+    // `limit` is the page size
+    let lockups: HashMap<u32, serde_json::Value> = sandbox
+        .lockup
+        .view(
+            "get_lockups_paged",
+            serde_json::json!({ "from_index": 0, "limit": 2 })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(2, lockups.len());
+    assert!(lockups.contains_key(&indexes[0]) && lockups.contains_key(&indexes[2]));
+
+    // `from_index` of the account lockups is the position in the order of indexes
+    let mut account_indexes = vec![];
+    for from_index in 0..3 {
+        let lockups: HashMap<u32, serde_json::Value> = sandbox
+            .lockup
+            .view(
+                "get_account_lockups",
+                serde_json::json!({
+                    "account_id": sandbox.owner.id(),
+                    "from_index": from_index,
+                    "limit": 1,
+                })
+                .to_string()
+                .into_bytes(),
+            )
+            .await?
+            .json()?;
+        assert_eq!(1, lockups.len());
+        account_indexes.extend(lockups.into_keys());
+    }
+    account_indexes.sort_unstable();
+    assert_eq!(account_indexes, vec![indexes[0], indexes[2], indexes[3]]);
+
+    // Zero `limit` still moves the cursor
+    let page = get_lockups_page(&sandbox.lockup, serde_json::json!({ "limit": 0 })).await?;
+    assert_eq!(1, page["lockups"].as_array().unwrap().len());
+    assert_eq!(page["next_cursor"], page["lockups"][0]["index"]);
+
+    let page = get_lockups_page(
+        &sandbox.lockup,
+        serde_json::json!({
+            "filter": {
+                "account_id": sandbox.owner.id(),
+                "claimable": true,
+            },
+        }),
+    )
+    .await?;
+    assert_eq!(2, page["lockups"].as_array().unwrap().len());
+
+    let unlock_on = page["lockups"][0]["unlock_on"].as_str().unwrap().to_owned();
+    let page = get_lockups_page(
+        &sandbox.lockup,
+        serde_json::json!({
+            "filter": {
+                "unlock_from": unlock_on,
+                "unlock_to": (unlock_on.parse::<u64>()? + 1).to_string(),
+            },
+        }),
+    )
+    .await?;
+    assert_eq!(
+        page["lockups"][0]["index"].as_u64().unwrap() as u32,
+        indexes[0]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn paginate_account_lockups_with_claim_between_pages() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+
+    for _ in 0..4 {
+        lockup::create_lockup(
+            &sandbox,
+            amount,
+            serde_json::json!({
+                "duration": SECOND,
+                "account_id": sandbox.owner.id(),
+            }),
+        )
+        .await?;
+    }
+    worker.fast_forward(100).await?;
+
+    let page_indexes = |page: &serde_json::Value| -> Vec<u32> {
+        page["lockups"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|lockup| lockup["index"].as_u64().unwrap() as u32)
+            .collect()
+    };
+    let filter = serde_json::json!({ "account_id": sandbox.owner.id() });
+    let page = get_lockups_page(
+        &sandbox.lockup,
+        serde_json::json!({ "limit": 2, "filter": filter }),
+    )
+    .await?;
+    let first_page = page_indexes(&page);
+    assert_eq!(2, first_page.len());
+    assert!(first_page[0] < first_page[1]);
+    assert_eq!(page["next_cursor"], serde_json::json!(first_page[1]));
+
+    // Removal of the first lockup doesn't move the rest of them
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "claim")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({ "index": first_page[0] }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let page = get_lockups_page(
+        &sandbox.lockup,
+        serde_json::json!({ "cursor": page["next_cursor"], "limit": 2, "filter": filter }),
+    )
+    .await?;
+    let second_page = page_indexes(&page);
+    assert!(page["next_cursor"].is_null());
+
+    let mut live: Vec<u32> = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id())
+        .await?
+        .into_keys()
+        .collect();
+    live.sort_unstable();
+    assert_eq!(3, live.len());
+    let collected: Vec<u32> = [&first_page[1..], &second_page[..]].concat();
+    assert_eq!(collected, live);
+
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use near_sdk::serde_json;
+use std::collections::HashMap;
+use test_utils::{token, utils::*, SandboxEnvironment};
+
+async fn create_lockup(sandbox: &SandboxEnvironment, amount: u128) -> anyhow::Result<u32> {
+    // Only staking contract can create lockups
+    // so transfer funds to staking contract
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer")
+        .deposit(YOCTO)
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.staking.id(),
+            "amount": amount.to_string(),
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    sandbox
+        .staking
+        .as_account()
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.lockup.id(),
+            "amount": amount.to_string(),
+            "msg": serde_json::json!({
+                "duration": SECOND,
+                "account_id": sandbox.owner.id(),
+            }).to_string()
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let lockups: HashMap<u32, serde_json::Value> = sandbox
+        .owner
+        .view(
+            sandbox.lockup.id(),
+            "get_account_lockups",
+            serde_json::json!({
+                "account_id": sandbox.owner.id(),
+            })
+            .to_string()
+            .into_bytes(),
+        )
+        .await?
+        .json()?;
+
+    Ok(*lockups.keys().next().expect("No lockups created!"))
+}
+
+#[tokio::test]
+async fn claim_and_restake() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "set_staking_account_id")
+        .deposit(YOCTO)
+        .args_json(serde_json::json!({ "staking_account_id": sandbox.staking.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Make storage deposit for owner
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let lockup_index = create_lockup(&sandbox, amount).await?;
+    worker.fast_forward(100).await?;
+
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "claim_and_restake")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({ "index": lockup_index }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    assert_eq!(
+        amount * 1_000,
+        token::ft_balance_of(&sandbox.staking, sandbox.owner.id()).await?
+    );
+    let lockup: Option<serde_json::Value> = sandbox
+        .lockup
+        .view(
+            "get_lockup",
+            serde_json::json!({ "index": lockup_index })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert!(lockup.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn claim_and_restake_rollback() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "set_staking_account_id")
+        .deposit(YOCTO)
+        .args_json(serde_json::json!({ "staking_account_id": sandbox.staking.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let lockup_index = create_lockup(&sandbox, amount).await?;
+    worker.fast_forward(100).await?;
+
+    // Owner isn't registered in staking, so tokens are refunded
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "claim_and_restake")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({ "index": lockup_index }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let lockup: serde_json::Value = sandbox
+        .lockup
+        .view(
+            "get_lockup",
+            serde_json::json!({ "index": lockup_index })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(lockup["amount"], amount.to_string());
+    assert_eq!(lockup["is_claimed"], false);
+    assert_eq!(
+        amount,
+        token::ft_balance_of(&sandbox.token, sandbox.lockup.id()).await?
+    );
+
+    Ok(())
+}
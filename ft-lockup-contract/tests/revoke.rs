@@ -0,0 +1,133 @@
+use near_sdk::serde_json;
+use test_utils::{lockup, token, utils::*, SandboxEnvironment};
+
+#[tokio::test]
+async fn revoke() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+
+    lockup::create_lockup(
+        &sandbox,
+        amount,
+        serde_json::json!({
+            "account_id": sandbox.backend.id(),
+            "schedule": { "duration": WEEK },
+            "revocable_by": sandbox.owner.id(),
+        }),
+    )
+    .await?;
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.backend.id()).await?;
+    let lockup_index = lockups.keys().next().expect("No lockups created!");
+
+    worker.fast_forward(100).await?;
+
+    // Only `revocable_by` can revoke
+    let result = sandbox
+        .backend
+        .call(sandbox.lockup.id(), "revoke")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({
+            "index": lockup_index,
+            "account_id": sandbox.backend.id(),
+        }))
+        .transact()
+        .await?
+        .into_result();
+    assert!(result.is_err());
+    let outcome = result.unwrap_err();
+    let failure = outcome.receipt_failures()[0];
+    assert!(format!("{:?}", failure).contains("Not allowed to revoke the lockup"));
+
+    let balance = token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await?;
+    let outcome = sandbox
+        .owner
+        .call(sandbox.lockup.id(), "revoke")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({
+            "index": lockup_index,
+            "account_id": sandbox.backend.id(),
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"lockup_revoke\"")));
+    let refund = token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await? - balance;
+    assert!(refund > 0);
+    assert!(refund < amount);
+
+    // The vested tokens stay for the beneficiary
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.backend.id()).await?;
+    let lockup = &lockups[lockup_index];
+    assert_eq!(lockup["remaining"], (amount - refund).to_string());
+    assert!(lockup["revocation"]["revoked_at"].is_string());
+
+    // Vesting is stopped
+    worker.fast_forward(100).await?;
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.backend.id()).await?;
+    assert_eq!(
+        lockups[lockup_index]["vested"],
+        (amount - refund).to_string()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn revoke_error_transfer_tokens() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+    let refund = sandbox
+        .owner
+        .create_subaccount("refund")
+        .initial_balance(10 * NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // `refund` do not have storage deposit on token contract
+    lockup::create_lockup(
+        &sandbox,
+        amount,
+        serde_json::json!({
+            "account_id": sandbox.backend.id(),
+            "duration": WEEK,
+            "revocable_by": sandbox.owner.id(),
+            "refund_account_id": refund.id(),
+        }),
+    )
+    .await?;
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.backend.id()).await?;
+    let lockup_index = lockups.keys().next().expect("No lockups created!");
+
+    let outcome = sandbox
+        .owner
+        .call(sandbox.lockup.id(), "revoke")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({
+            "index": lockup_index,
+            "account_id": sandbox.backend.id(),
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"lockup_revoke_rollback\"")));
+    assert_eq!(0, token::ft_balance_of(&sandbox.token, refund.id()).await?);
+
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.backend.id()).await?;
+    let lockup = &lockups[lockup_index];
+    assert_eq!(lockup["remaining"], amount.to_string());
+    assert!(lockup["revocation"]["revoked_at"].is_null());
+
+    Ok(())
+}
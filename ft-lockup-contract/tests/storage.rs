@@ -0,0 +1,104 @@
+use near_contract_standards::storage_management::StorageBalance;
+use near_sdk::serde_json;
+use test_utils::{lockup, utils::*, SandboxEnvironment};
+use workspaces::{AccountId, Contract};
+
+async fn storage_balance_of(
+    contract: &Contract,
+    account_id: &AccountId,
+) -> anyhow::Result<Option<StorageBalance>> {
+    Ok(contract
+        .view(
+            "storage_balance_of",
+            serde_json::json!({ "account_id": account_id })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?)
+}
+
+#[tokio::test]
+async fn lockup_storage_is_refunded_on_claim() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+
+    let initial = storage_balance_of(&sandbox.lockup, sandbox.staking.id())
+        .await?
+        .expect("Staking contract is not registered");
+
+    lockup::create_lockup(
+        &sandbox,
+        100 * LIS,
+        serde_json::json!({
+            "duration": SECOND,
+            "account_id": sandbox.owner.id(),
+        }),
+    )
+    .await?;
+    let charged = storage_balance_of(&sandbox.lockup, sandbox.staking.id())
+        .await?
+        .unwrap();
+    assert_eq!(initial.total, charged.total);
+    assert!(charged.available.0 < initial.available.0);
+
+    worker.fast_forward(100).await?;
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id()).await?;
+    let lockup_index = lockups.keys().next().expect("No lockups created!");
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "claim")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({ "index": lockup_index }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let refunded = storage_balance_of(&sandbox.lockup, sandbox.staking.id())
+        .await?
+        .unwrap();
+    assert_eq!(initial.available, refunded.available);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn create_lockup_without_storage_deposit() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "extend_deposit_whitelist")
+        .deposit(YOCTO)
+        .args_json(serde_json::json!({ "account_ids": [sandbox.owner.id()] }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let outcome = sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.lockup.id(),
+            "amount": LIS.to_string(),
+            "msg": serde_json::json!({
+                "duration": WEEK,
+                "account_id": sandbox.owner.id(),
+            })
+            .to_string(),
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let failure = outcome.receipt_failures()[0];
+    assert!(format!("{:?}", failure).contains("is not registered"));
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id()).await?;
+    assert!(lockups.is_empty());
+
+    Ok(())
+}
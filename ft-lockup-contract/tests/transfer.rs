@@ -0,0 +1,97 @@
+use near_sdk::serde_json;
+use test_utils::{lockup, utils::*, SandboxEnvironment};
+
+#[tokio::test]
+async fn transfer_lockup() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+
+    lockup::create_lockup(
+        &sandbox,
+        100 * LIS,
+        serde_json::json!({
+            "duration": WEEK,
+            "account_id": sandbox.owner.id(),
+        }),
+    )
+    .await?;
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id()).await?;
+    let lockup_index = lockups.keys().next().expect("No lockups created!");
+
+    // The sender pays for the lockup set of the receiver
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "storage_deposit")
+        .deposit(NEAR)
+        .args_json(serde_json::json!({}))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let outcome = sandbox
+        .owner
+        .call(sandbox.lockup.id(), "transfer_lockup")
+        .deposit(YOCTO)
+        .args_json(serde_json::json!({
+            "index": lockup_index,
+            "receiver_id": sandbox.backend.id(),
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+    assert!(outcome.logs().contains(
+        &format!(
+            r#"EVENT_JSON:{{"data":[{{"index":{},"new_account_id":"{}","old_account_id":"{}"}}],"event":"lockup_transfer","standard":"realis","version":"1.0.0"}}"#,
+            lockup_index,
+            sandbox.backend.id(),
+            sandbox.owner.id()
+        )
+        .as_str()
+    ));
+
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id()).await?;
+    assert!(lockups.is_empty());
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.backend.id()).await?;
+    assert!(lockups.contains_key(lockup_index));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn transfer_not_transferable_lockup() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+
+    lockup::create_lockup(
+        &sandbox,
+        100 * LIS,
+        serde_json::json!({
+            "duration": WEEK,
+            "account_id": sandbox.owner.id(),
+            "transferable": false,
+        }),
+    )
+    .await?;
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id()).await?;
+    let lockup_index = lockups.keys().next().expect("No lockups created!");
+
+    let result = sandbox
+        .owner
+        .call(sandbox.lockup.id(), "transfer_lockup")
+        .deposit(YOCTO)
+        .args_json(serde_json::json!({
+            "index": lockup_index,
+            "receiver_id": sandbox.backend.id(),
+        }))
+        .transact()
+        .await?
+        .into_result();
+
+    assert!(result.is_err());
+    let outcome = result.unwrap_err();
+    let failure = outcome.receipt_failures()[0];
+    assert!(failure.is_failure());
+    assert!(format!("{:?}", failure).contains("The lockup is not transferable"));
+
+    Ok(())
+}
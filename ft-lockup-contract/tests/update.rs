@@ -1,4 +1,5 @@
-use test_utils::{lockup, SandboxEnvironment};
+use near_sdk::serde_json;
+use test_utils::{lockup, token, utils::*, SandboxEnvironment};
 
 #[tokio::test]
 async fn update() -> anyhow::Result<()> {
@@ -7,6 +8,166 @@ async fn update() -> anyhow::Result<()> {
 
     let amount = lockup::get_num_lockups(&contract).await?;
     assert_eq!(amount, 0_u64);
+    assert_eq!(get_state_version(&contract).await?, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn update_with_legacy_lockups() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let mut sandbox = SandboxEnvironment::with_legacy_lockup(&worker).await?;
+    let amount = 100 * LIS;
+
+    // Lockups with the account sets of the old layout
+    for _ in 0..3 {
+        lockup::create_lockup(
+            &sandbox,
+            amount,
+            serde_json::json!({
+                "duration": SECOND,
+                "account_id": sandbox.owner.id(),
+            }),
+        )
+        .await?;
+    }
+
+    sandbox.upgrade_lockup().await?;
+    assert_eq!(get_state_version(&sandbox.lockup).await?, 1);
+    let mut indexes: Vec<u32> = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id())
+        .await?
+        .into_keys()
+        .collect();
+    indexes.sort_unstable();
+    assert_eq!(3, indexes.len());
+
+    // The claim moves the legacy set to the new layout
+    worker.fast_forward(100).await?;
+    let balance = token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await?;
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "claim")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({ "index": indexes[0] }))
+        .transact()
+        .await?
+        .into_result()?;
+    assert_eq!(
+        balance + amount,
+        token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await?
+    );
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id()).await?;
+    assert_eq!(2, lockups.len());
+    assert!(!lockups.contains_key(&indexes[0]));
+
+    // Migrated lockups are not transferable
+    let result = sandbox
+        .owner
+        .call(sandbox.lockup.id(), "transfer_lockup")
+        .deposit(YOCTO)
+        .args_json(serde_json::json!({
+            "index": indexes[1],
+            "receiver_id": sandbox.backend.id(),
+        }))
+        .transact()
+        .await?
+        .into_result();
+    let outcome = result.unwrap_err();
+    let failure = outcome.receipt_failures()[0];
+    assert!(format!("{:?}", failure).contains("The lockup is not transferable"));
+
+    // A new lockup joins the migrated set and can be transferred
+    lockup::create_lockup(
+        &sandbox,
+        amount,
+        serde_json::json!({
+            "duration": WEEK,
+            "account_id": sandbox.owner.id(),
+        }),
+    )
+    .await?;
+    let new_index = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id())
+        .await?
+        .into_keys()
+        .find(|index| !indexes.contains(index))
+        .expect("No lockups created!");
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "storage_deposit")
+        .deposit(NEAR)
+        .args_json(serde_json::json!({}))
+        .transact()
+        .await?
+        .into_result()?;
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "transfer_lockup")
+        .deposit(YOCTO)
+        .args_json(serde_json::json!({
+            "index": new_index,
+            "receiver_id": sandbox.backend.id(),
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.backend.id()).await?;
+    assert!(lockups.contains_key(&new_index));
+
+    // The rest of the legacy lockups are claimed at once
+    let balance = token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await?;
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "claim_all")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({}))
+        .transact()
+        .await?
+        .into_result()?;
+    assert_eq!(
+        balance + 2 * amount,
+        token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await?
+    );
+    let lockups = lockup::get_account_lockups(&sandbox.lockup, sandbox.owner.id()).await?;
+    assert!(lockups.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn set_owner() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+
+    // Only owner can change the owner
+    let result = sandbox
+        .backend
+        .call(sandbox.lockup.id(), "set_owner")
+        .args_json(serde_json::json!({ "owner": sandbox.backend.id() }))
+        .transact()
+        .await?
+        .into_result();
+    assert!(result.is_err());
+
+    let outcome = sandbox
+        .owner
+        .call(sandbox.lockup.id(), "set_owner")
+        .args_json(serde_json::json!({ "owner": sandbox.backend.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"owner_change\"")));
+
+    let owner_id: String = sandbox
+        .lockup
+        .view("get_owner", serde_json::json!({}).to_string().into_bytes())
+        .await?
+        .json()?;
+    assert_eq!(owner_id, sandbox.backend.id().to_string());
 
     Ok(())
 }
@@ -0,0 +1,175 @@
+use near_sdk::serde_json;
+use std::collections::HashMap;
+use test_utils::{token, utils::*, SandboxEnvironment};
+
+#[tokio::test]
+async fn claim_vested_part() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+
+    // Only staking contract can create lockups
+    // so transfer funds to staking contract
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer")
+        .deposit(YOCTO)
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.staking.id(),
+            "amount": amount.to_string(),
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Create lock vesting linearly during a week
+    sandbox
+        .staking
+        .as_account()
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.lockup.id(),
+            "amount": amount.to_string(),
+            "msg": serde_json::json!({
+                "account_id": sandbox.owner.id(),
+                "schedule": {
+                    "duration": WEEK,
+                },
+            }).to_string()
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    worker.fast_forward(100).await?;
+
+    let lockups: HashMap<u32, serde_json::Value> = sandbox
+        .owner
+        .view(
+            sandbox.lockup.id(),
+            "get_account_lockups",
+            serde_json::json!({
+                "account_id": sandbox.owner.id(),
+            })
+            .to_string()
+            .into_bytes(),
+        )
+        .await?
+        .json()?;
+    let lockup_index = lockups.keys().next().expect("No lockups created!");
+
+    let balance = token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await?;
+    sandbox
+        .owner
+        .call(sandbox.lockup.id(), "claim")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({
+            "index": lockup_index,
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+    let claimed = token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await? - balance;
+    assert!(claimed > 0);
+    assert!(claimed < amount);
+
+    // The rest of the tokens stays in the lockup
+    let lockup: serde_json::Value = sandbox
+        .owner
+        .view(
+            sandbox.lockup.id(),
+            "get_lockup",
+            serde_json::json!({ "index": lockup_index })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(lockup["amount"], amount.to_string());
+    assert_eq!(lockup["claimed"], claimed.to_string());
+    assert_eq!(lockup["remaining"], (amount - claimed).to_string());
+    assert_eq!(lockup["is_claimed"], false);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn claim_before_cliff() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer")
+        .deposit(YOCTO)
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.staking.id(),
+            "amount": amount.to_string(),
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    sandbox
+        .staking
+        .as_account()
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.lockup.id(),
+            "amount": amount.to_string(),
+            "msg": serde_json::json!({
+                "account_id": sandbox.owner.id(),
+                "schedule": {
+                    "cliff": DAY,
+                    "duration": WEEK,
+                    "tranche": DAY,
+                },
+            }).to_string()
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let lockups: HashMap<u32, serde_json::Value> = sandbox
+        .owner
+        .view(
+            sandbox.lockup.id(),
+            "get_account_lockups",
+            serde_json::json!({
+                "account_id": sandbox.owner.id(),
+            })
+            .to_string()
+            .into_bytes(),
+        )
+        .await?
+        .json()?;
+    let (lockup_index, lockup) = lockups.iter().next().expect("No lockups created!");
+    assert_eq!(lockup["vested"], "0");
+    assert_eq!(lockup["schedule"]["cliff"], DAY.0.to_string());
+
+    let result = sandbox
+        .owner
+        .call(sandbox.lockup.id(), "claim")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({
+            "index": lockup_index,
+        }))
+        .transact()
+        .await?
+        .into_result();
+
+    assert!(result.is_err());
+    let outcome = result.unwrap_err();
+    let failure = outcome.receipt_failures()[0];
+    assert!(failure.is_failure());
+    assert!(format!("{:?}", failure).contains("Nothing is vested yet"));
+
+    Ok(())
+}
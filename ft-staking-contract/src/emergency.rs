@@ -0,0 +1,148 @@
+use crate::*;
+use common::events::{self, RealisEvent};
+use near_contract_standards::upgrade::Ownable;
+use near_sdk::is_promise_success;
+
+#[near_bindgen]
+impl Contract {
+    /// Turn the emergency mode on or off.
+    /// In the emergency mode staking and rewards are disabled
+    /// and stakers can withdraw their tokens bypassing the lockup.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Caller must be the owner.
+    #[payable]
+    pub fn set_emergency_mode(&mut self, enabled: bool) {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(
+            self.emergency_mode != enabled,
+            "The emergency mode is already set"
+        );
+        self.emergency_mode = enabled;
+
+        events::EmergencyMode { enabled }.emit();
+    }
+
+    pub fn is_emergency_mode(&self) -> bool {
+        self.emergency_mode
+    }
+
+    /// Burn xtokens of the caller and transfer the pro-rata part of the pool
+    /// directly to the caller. Locks are ignored.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * The emergency mode must be on.
+    #[payable]
+    pub fn emergency_withdraw(&mut self, xtoken_amount: Option<U128>) -> Promise {
+        assert_one_yocto();
+        require!(self.emergency_mode, "The emergency mode is off");
+        let account_id = env::predecessor_account_id();
+        let account_xtokens_amount = self.accounts.get(&account_id).unwrap_or_default();
+        let xtoken_amount = xtoken_amount.map(|a| a.0).unwrap_or(account_xtokens_amount);
+        require!(xtoken_amount > 0, "The xtoken_amount should not be zero");
+        require!(
+            xtoken_amount <= account_xtokens_amount,
+            "Not enough xtokens"
+        );
+        let amount = mul_div(
+            xtoken_amount,
+            self.staked_supply(),
+            self.total_xtoken_supply,
+        );
+        require!(amount > 0, "Too small xtokens amount");
+
+        self.total_supply = self
+            .total_supply
+            .checked_sub(amount)
+            .unwrap_or_else(|| env::panic_str("Sub will overflow"));
+        self.total_xtoken_supply = self
+            .total_xtoken_supply
+            .checked_sub(xtoken_amount)
+            .unwrap_or_else(|| env::panic_str("Sub will overflow"));
+        self.migrate_reward_weight(&account_id);
+        let account_xtokens_amount = account_xtokens_amount
+            .checked_sub(xtoken_amount)
+            .unwrap_or_else(|| env::panic_str("Sub will overflow"));
+        self.accounts.insert(&account_id, &account_xtokens_amount);
+        // Withdrawn xtokens leave the lock, an empty lock is kept until the transfer succeeds
+        let unlocked_xtoken_amount = match self.locks.get(&account_id) {
+            Some(mut lock) if lock.xtoken_amount > account_xtokens_amount => {
+                let unlocked = lock.xtoken_amount - account_xtokens_amount;
+                lock.xtoken_amount = account_xtokens_amount;
+                self.locks.insert(&account_id, &lock);
+                unlocked
+            }
+            _ => 0,
+        };
+
+        near_contract_standards::fungible_token::events::FtBurn {
+            owner_id: &account_id,
+            amount: &xtoken_amount.into(),
+            memo: Some("Emergency withdraw"),
+        }
+        .emit();
+        events::EmergencyWithdraw {
+            account_id: &account_id,
+            amount: amount.into(),
+            xtoken_amount: xtoken_amount.into(),
+        }
+        .emit();
+        self.update_reward_weight(&account_id);
+
+        ext_ft_core::ext(self.token_account_id.clone())
+            .with_static_gas(GAS_FOR_WITHDRAW)
+            .with_attached_deposit(ONE_YOCTO)
+            .ft_transfer(account_id.clone(), amount.into(), None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_WITHDRAW_CALLBACK)
+                    .emergency_withdraw_callback(
+                        account_id,
+                        amount.into(),
+                        xtoken_amount.into(),
+                        unlocked_xtoken_amount.into(),
+                    ),
+            )
+    }
+
+    /// `unlocked_xtoken_amount` is the part of the withdrawn xtokens that left the lock.
+    /// NFT boost is returned once the last xtokens are withdrawn successfully.
+    #[private]
+    pub fn emergency_withdraw_callback(
+        &mut self,
+        account_id: AccountId,
+        amount: U128,
+        xtoken_amount: U128,
+        unlocked_xtoken_amount: U128,
+    ) {
+        let lock = self.locks.get(&account_id);
+        if is_promise_success() {
+            if lock.map(|lock| lock.xtoken_amount == 0).unwrap_or_default() {
+                self.locks.remove(&account_id);
+            }
+            if self.accounts.get(&account_id).unwrap_or_default() == 0 {
+                self.return_nft_internal(&account_id);
+            }
+            return;
+        }
+
+        // Rollback the withdrawal with the lock if transfer fail
+        if let Some(mut lock) = lock.filter(|_| unlocked_xtoken_amount.0 > 0) {
+            lock.xtoken_amount = lock
+                .xtoken_amount
+                .checked_add(unlocked_xtoken_amount.0)
+                .unwrap_or_else(|| env::panic_str("Add will overflow"));
+            self.locks.insert(&account_id, &lock);
+        }
+        self.unstake_rollback_internal(&account_id, amount.0, xtoken_amount.0, amount.0);
+    }
+}
+
+impl Contract {
+    pub fn assert_not_emergency(&self) {
+        require!(!self.emergency_mode, "Disabled in the emergency mode");
+    }
+}
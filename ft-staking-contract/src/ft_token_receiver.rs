@@ -9,8 +9,30 @@ use near_sdk::{
 #[serde(crate = "near_sdk::serde")]
 pub enum FtMessage {
     Stake,
-    StakeFor { account_id: AccountId },
+    /// Stake for `account_id`
+    StakeFor {
+        account_id: AccountId,
+    },
+    /// Stake for `account_id` by its operator,
+    /// the received xtokens are taken from the allowance of the grant
+    RestakeFor {
+        account_id: AccountId,
+    },
     AddToPool,
+    /// Stake and set `referrer` as the referrer of the sender,
+    /// if the sender has no referrer yet
+    StakeWithReferral {
+        referrer: AccountId,
+    },
+    /// Stake and lock the received xtokens for `weeks`
+    Lock {
+        weeks: u32,
+    },
+    /// Stake, add the received xtokens to the active lock
+    /// and prolong it to `weeks` from now
+    ExtendLock {
+        weeks: u32,
+    },
 }
 
 #[near_bindgen]
@@ -21,26 +43,73 @@ impl FungibleTokenReceiver for Contract {
         amount: U128,
         msg: String,
     ) -> PromiseOrValue<U128> {
-        require!(
-            env::predecessor_account_id() == self.token_account_id,
-            "Invalid token ID"
-        );
+        self.assert_not_emergency();
         let amount = amount.0;
+        let token_account_id = env::predecessor_account_id();
+        if token_account_id != self.token_account_id {
+            // Only rewards can be deposited in other tokens
+            return match serde_json::from_str::<FtMessage>(&msg) {
+                Ok(FtMessage::AddToPool) => PromiseOrValue::Value(
+                    self.add_reward_internal(&token_account_id, amount).into(),
+                ),
+                _ => env::panic_str("Invalid token ID"),
+            };
+        }
 
-        match serde_json::from_str::<FtMessage>(&msg)
-            .unwrap_or_else(|_| env::panic_str("Invalid msg"))
-        {
+        let message = serde_json::from_str::<FtMessage>(&msg)
+            .unwrap_or_else(|_| env::panic_str("Invalid msg"));
+        let staked = match message {
             FtMessage::Stake => {
                 self.assert_register(&sender_id);
-                self.stake_internal(&sender_id, amount)
+                let staked = self.stake_limited_amount(&sender_id, amount);
+                self.stake_internal(&sender_id, staked);
+                staked
             }
             FtMessage::StakeFor { account_id } => {
                 self.assert_register(&account_id);
-                self.stake_internal(&account_id, amount)
+                let staked = self.stake_limited_amount(&account_id, amount);
+                self.stake_internal(&account_id, staked);
+                staked
             }
-            FtMessage::AddToPool => self.add_to_pool_internal(amount),
-        }
+            FtMessage::RestakeFor { account_id } => {
+                self.assert_register(&account_id);
+                let staked = self.stake_limited_amount(&account_id, amount);
+                let xtokens_amount = self.stake_internal(&account_id, staked);
+                self.use_operator_grant(&account_id, &sender_id, xtokens_amount);
+                staked
+            }
+            FtMessage::StakeWithReferral { referrer } => {
+                self.assert_register(&sender_id);
+                self.set_referrer_internal(&sender_id, &referrer);
+                let staked = self.stake_limited_amount(&sender_id, amount);
+                self.stake_internal(&sender_id, staked);
+                staked
+            }
+            FtMessage::AddToPool => {
+                self.add_to_pool_internal(amount);
+                amount
+            }
+            FtMessage::Lock { weeks } => {
+                self.assert_register(&sender_id);
+                let staked = self.stake_limited_amount(&sender_id, amount);
+                let xtokens_amount = self.stake_internal(&sender_id, staked);
+                self.lock_internal(&sender_id, xtokens_amount, weeks);
+                staked
+            }
+            FtMessage::ExtendLock { weeks } => {
+                self.assert_register(&sender_id);
+                let staked = self.stake_limited_amount(&sender_id, amount);
+                let xtokens_amount = self.stake_internal(&sender_id, staked);
+                self.extend_lock_internal(&sender_id, xtokens_amount, weeks);
+                staked
+            }
+        };
 
-        PromiseOrValue::Value(U128(0))
+        // Return the part of the tokens exceeding the stake limits
+        PromiseOrValue::Value(U128(
+            amount
+                .checked_sub(staked)
+                .unwrap_or_else(|| env::panic_str("Sub will overflow")),
+        ))
     }
 }
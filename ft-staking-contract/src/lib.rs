@@ -1,8 +1,11 @@
+use common::events::{self, RealisEvent};
+use limits::StakeLimits;
+use lock::{RewardWeight, StakeLock};
 use near_contract_standards::fungible_token::core::ext_ft_core;
 use near_sdk::{
     assert_one_yocto,
     borsh::{self, BorshDeserialize, BorshSerialize},
-    collections::LookupMap,
+    collections::{LookupMap, UnorderedMap, UnorderedSet, Vector},
     env,
     json_types::{U128, U64},
     near_bindgen, require,
@@ -10,11 +13,24 @@ use near_sdk::{
     AccountId, Balance, Gas, PanicOnDefault, Promise, PromiseError, PromiseOrValue, StorageUsage,
     ONE_YOCTO,
 };
-use xtoken::XTokenCost;
+use nft_boost::StakedNft;
+use operator::OperatorGrant;
+use referral::ReferralReward;
+use rewards::{AccountReward, RewardToken, MAX_REWARD_TOKENS};
+use stats::PriceSnapshot;
+use xtoken::{mul_div, mul_div_ceil, XTokenCost};
 
+pub mod emergency;
 pub mod ft_token_core;
 pub mod ft_token_receiver;
+pub mod limits;
+pub mod lock;
 pub mod metadata;
+pub mod nft_boost;
+pub mod operator;
+pub mod referral;
+pub mod rewards;
+pub mod stats;
 pub mod storage_impl;
 pub mod update;
 pub mod xtoken;
@@ -31,6 +47,13 @@ pub const WEEK: U64 = U64(7 * DAY.0);
 
 pub const GAS_FOR_UNSTAKE: Gas = Gas(40_000_000_000_000);
 pub const GAS_FOR_UNSTAKE_CALLBACK: Gas = Gas(20_000_000_000_000);
+pub const GAS_FOR_REWARD_TRANSFER: Gas = Gas(10_000_000_000_000);
+pub const GAS_FOR_REWARD_CALLBACK: Gas = Gas(10_000_000_000_000);
+pub const GAS_FOR_WITHDRAW: Gas = Gas(10_000_000_000_000);
+/// Covers the return of the staked NFT after the last xtokens are withdrawn
+pub const GAS_FOR_WITHDRAW_CALLBACK: Gas = Gas(40_000_000_000_000);
+pub const GAS_FOR_NFT_TRANSFER: Gas = Gas(15_000_000_000_000);
+pub const GAS_FOR_NFT_TRANSFER_CALLBACK: Gas = Gas(10_000_000_000_000);
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
@@ -50,6 +73,54 @@ pub struct Contract {
     xtoken_cost: XTokenCost,
     /// Storage usage for one account
     account_storage_usage: StorageUsage,
+    /// AccountID -> Locked xtokens of the account.
+    locks: LookupMap<AccountId, StakeLock>,
+    /// AccountID -> Account reward weight on the last checkpoint.
+    reward_weights: LookupMap<AccountId, RewardWeight>,
+    /// Sum of the all stored reward weights
+    total_reward_weight: Balance,
+    /// Week -> Sum of the lock boosts ending at the start of the week.
+    boost_ends: LookupMap<u64, Balance>,
+    /// (Reward token account id, Week) -> `reward_per_weight` and `referral_per_weight`
+    /// at the start of the week with ending lock boosts.
+    boost_end_snapshots: LookupMap<(AccountId, u64), (u128, u128)>,
+    /// Last week the ending lock boosts are subtracted from the total reward weight
+    processed_week: u64,
+    /// Reward token account id -> Reward distribution state.
+    reward_tokens: UnorderedMap<AccountId, RewardToken>,
+    /// (AccountID, Reward token account id) -> Rewards of the account.
+    account_rewards: LookupMap<(AccountId, AccountId), AccountReward>,
+    /// (AccountID, Operator account id) -> Permission of the operator.
+    operators: LookupMap<(AccountId, AccountId), OperatorGrant>,
+    /// Ring buffer of the weekly xtoken cost snapshots
+    price_snapshots: Vector<PriceSnapshot>,
+    /// Number of the all recorded price snapshots
+    num_price_snapshots: u64,
+    /// Tokens left in the pool by rounding of the stakes
+    dust: Balance,
+    /// Limits of the stakes made with `ft_transfer_call`
+    stake_limits: StakeLimits,
+    /// Staking is disabled and stakers can withdraw bypassing the lockup
+    emergency_mode: bool,
+    /// AccountID -> Referrer of the account.
+    referrers: LookupMap<AccountId, AccountId>,
+    /// Referrer account id -> Number of the referees.
+    referees_count: LookupMap<AccountId, u32>,
+    /// (Referrer account id, Reward token account id) -> Referral rewards.
+    referral_rewards: LookupMap<(AccountId, AccountId), ReferralReward>,
+    /// Part of the referee rewards that goes to the referrer, in basis points
+    referral_fee: u32,
+    /// Reward token account id -> Accumulated referral cut per unit of the reward weight,
+    /// multiplied by `REWARD_PRECISION`.
+    referral_per_weight: LookupMap<AccountId, u128>,
+    /// (Referee account id, Reward token account id) -> Value of `referral_per_weight` on the last settlement.
+    referral_per_weight_paid: LookupMap<(AccountId, AccountId), u128>,
+    /// NFT contracts that can boost the reward weight
+    nft_whitelist: UnorderedSet<AccountId>,
+    /// (NFT contract account id, Series) -> Reward weight multiplier in basis points.
+    nft_multipliers: UnorderedMap<(AccountId, String), u32>,
+    /// AccountID -> NFT boosting the account.
+    staked_nfts: LookupMap<AccountId, StakedNft>,
 }
 
 #[near_bindgen]
@@ -69,54 +140,121 @@ impl Contract {
             total_xtoken_supply: 0,
             xtoken_cost: XTokenCost::default(),
             account_storage_usage: 0,
+            locks: LookupMap::new(b"l"),
+            reward_weights: LookupMap::new(b"w"),
+            total_reward_weight: 0,
+            boost_ends: LookupMap::new(b"d"),
+            boost_end_snapshots: LookupMap::new(b"k"),
+            processed_week: lock::current_week(),
+            reward_tokens: UnorderedMap::new(b"r"),
+            account_rewards: LookupMap::new(b"c"),
+            operators: LookupMap::new(b"o"),
+            price_snapshots: Vector::new(b"p"),
+            num_price_snapshots: 0,
+            dust: 0,
+            stake_limits: StakeLimits::default(),
+            emergency_mode: false,
+            referrers: LookupMap::new(b"f"),
+            referees_count: LookupMap::new(b"n"),
+            referral_rewards: LookupMap::new(b"e"),
+            referral_fee: 0,
+            referral_per_weight: LookupMap::new(b"g"),
+            referral_per_weight_paid: LookupMap::new(b"h"),
+            nft_whitelist: UnorderedSet::new(b"t"),
+            nft_multipliers: UnorderedMap::new(b"m"),
+            staked_nfts: LookupMap::new(b"s"),
         };
         this.measure_account_storage_usage();
+        common::state_version::set_state_version(update::STATE_VERSION);
         this
     }
 
     fn measure_account_storage_usage(&mut self) {
         let initial_storage_usage = env::storage_usage();
         let tmp_account_id = AccountId::new_unchecked("a".repeat(64));
+        let tmp_keys: Vec<_> = (0..MAX_REWARD_TOKENS)
+            .map(|i| {
+                (
+                    tmp_account_id.clone(),
+                    AccountId::new_unchecked(format!("{i:b<64}")),
+                )
+            })
+            .collect();
+        // The account with the reward weight, the lock and the rewards in every reward token
         self.accounts.insert(&tmp_account_id, &0u128);
+        self.reward_weights
+            .insert(&tmp_account_id, &RewardWeight::unboosted(0));
+        self.locks.insert(
+            &tmp_account_id,
+            &StakeLock {
+                xtoken_amount: 0,
+                locked_on: 0,
+                unlock_on: 0,
+            },
+        );
+        for tmp_key in &tmp_keys {
+            self.account_rewards
+                .insert(tmp_key, &AccountReward::default());
+            self.referral_per_weight_paid.insert(tmp_key, &0);
+        }
         self.account_storage_usage = env::storage_usage()
             .checked_sub(initial_storage_usage)
             .unwrap_or_else(|| env::panic_str("Sub will overflow"));
         self.accounts.remove(&tmp_account_id);
+        self.reward_weights.remove(&tmp_account_id);
+        self.locks.remove(&tmp_account_id);
+        for tmp_key in &tmp_keys {
+            self.account_rewards.remove(tmp_key);
+            self.referral_per_weight_paid.remove(tmp_key);
+        }
     }
 
     #[payable]
     pub fn unstake(&mut self, xtoken_amount: Option<U128>) -> Promise {
         assert_one_yocto();
-        let xtoken_amount = xtoken_amount.map(|a| a.0).unwrap_or_else(|| {
-            self.accounts
-                .get(&env::predecessor_account_id())
-                .unwrap_or_default()
-        });
+        let xtoken_amount = xtoken_amount
+            .map(|a| a.0)
+            .unwrap_or_else(|| self.unlocked_balance(&env::predecessor_account_id()));
         require!(xtoken_amount > 0, "The xtoken_amount should not be zero");
-        self.unstake_internal(&env::predecessor_account_id(), xtoken_amount)
+        self.unstake_internal(&env::predecessor_account_id(), xtoken_amount, None)
     }
 
+    /// `operator_id` is the operator that made the unstake, if any.
     #[private]
     pub fn transfer_on_unstake_callback(
         &mut self,
         account_id: AccountId,
         amount: U128,
+        xtoken_amount: U128,
+        operator_id: Option<AccountId>,
         #[callback_result] used: Result<U128, PromiseError>,
     ) {
-        let amount = amount
+        let refund = amount
             .0
             .checked_sub(used.map(|v| v.0).unwrap_or_default())
             .unwrap_or_else(|| env::panic_str("Sub will overflow"));
         // Rollback account stake
-        if amount > 0 {
-            self.stake_internal(&account_id, amount);
+        if refund > 0 {
+            let xtoken_refund =
+                self.unstake_rollback_internal(&account_id, amount.0, xtoken_amount.0, refund);
+            if let Some(operator_id) = operator_id {
+                self.restore_operator_allowance(&account_id, &operator_id, xtoken_refund);
+            }
         }
     }
 }
 
 impl Contract {
-    pub fn stake_internal(&mut self, account_id: &AccountId, amount: Balance) {
-        let xtokens_amount = self.xtoken_cost.convert_to_xtokens(amount);
+    pub fn stake_internal(&mut self, account_id: &AccountId, amount: Balance) -> Balance {
+        let shares = self
+            .xtoken_cost
+            .shares_for_deposit(amount)
+            .unwrap_or_else(|| env::panic_str("Too small amount"));
+        let xtokens_amount = shares.xtokens_amount;
+        self.dust = self
+            .dust
+            .checked_add(shares.dust)
+            .unwrap_or_else(|| env::panic_str("Add will overflow"));
         self.total_supply = self
             .total_supply
             .checked_add(amount)
@@ -125,6 +263,7 @@ impl Contract {
             .total_xtoken_supply
             .checked_add(xtokens_amount)
             .unwrap_or_else(|| env::panic_str("Add will overflow"));
+        self.migrate_reward_weight(account_id);
         let account_xtokens_amount = self
             .accounts
             .get(account_id)
@@ -142,10 +281,35 @@ impl Contract {
             memo: None,
         }
         .emit();
+
+        self.update_reward_weight(account_id);
+        xtokens_amount
     }
 
-    pub fn unstake_internal(&mut self, account_id: &AccountId, xtoken_amount: Balance) -> Promise {
-        let amount = self.xtoken_cost.convert_to_amount(xtoken_amount);
+    pub fn unstake_internal(
+        &mut self,
+        account_id: &AccountId,
+        xtoken_amount: Balance,
+        operator_id: Option<&AccountId>,
+    ) -> Promise {
+        require!(
+            xtoken_amount <= self.unlocked_balance(account_id),
+            "Not enough unlocked xtokens"
+        );
+        let amount = self
+            .xtoken_cost
+            .amount_for_redeem(xtoken_amount)
+            .unwrap_or_else(|| env::panic_str("Too small xtokens amount"));
+        self.assert_min_unstake(account_id, xtoken_amount, amount);
+        // Expired lock is not needed anymore
+        if self
+            .locks
+            .get(account_id)
+            .map(|lock| !lock.is_active())
+            .unwrap_or_default()
+        {
+            self.locks.remove(account_id);
+        }
         self.total_supply = self
             .total_supply
             .checked_sub(amount)
@@ -154,6 +318,7 @@ impl Contract {
             .total_xtoken_supply
             .checked_sub(xtoken_amount)
             .unwrap_or_else(|| env::panic_str("Sub will overflow"));
+        self.migrate_reward_weight(account_id);
         let account_xtokens_amount = self.accounts.get(account_id).unwrap_or_default();
         self.accounts.insert(
             account_id,
@@ -168,16 +333,20 @@ impl Contract {
             memo: None,
         }
         .emit();
+        self.update_reward_weight(account_id);
 
-        if amount == 0 {
-            env::panic_str("Too small xtokens amount");
+        // NFT boost is returned with the last xtokens
+        let mut reserved_gas = GAS_FOR_UNSTAKE.0;
+        if account_xtokens_amount == xtoken_amount && self.return_nft_internal(account_id).is_some()
+        {
+            reserved_gas += GAS_FOR_NFT_TRANSFER.0 + GAS_FOR_NFT_TRANSFER_CALLBACK.0;
         }
 
         ext_ft_core::ext(self.token_account_id.clone())
             .with_static_gas(
                 env::prepaid_gas()
                     .0
-                    .checked_sub(GAS_FOR_UNSTAKE.0)
+                    .checked_sub(reserved_gas)
                     .unwrap_or_else(|| env::panic_str("Sub will overflow"))
                     .into(),
             )
@@ -195,10 +364,73 @@ impl Contract {
             .then(
                 Self::ext(env::current_account_id())
                     .with_static_gas(GAS_FOR_UNSTAKE_CALLBACK)
-                    .transfer_on_unstake_callback(account_id.clone(), amount.into()),
+                    .transfer_on_unstake_callback(
+                        account_id.clone(),
+                        amount.into(),
+                        xtoken_amount.into(),
+                        operator_id.cloned(),
+                    ),
             )
     }
 
+    /// Restore the part of the stake burned in `unstake_internal` that was
+    /// refunded by the lockup and return the restored xtokens. Xtokens are restored in proportion to the refund,
+    /// rounded up in favour of the account. If the xtoken cost grew in between,
+    /// it is recalculated so the restored xtokens share the tokens added to the pool
+    /// as if they were never burned.
+    pub fn unstake_rollback_internal(
+        &mut self,
+        account_id: &AccountId,
+        amount: Balance,
+        xtoken_amount: Balance,
+        refund: Balance,
+    ) -> Balance {
+        let xtoken_refund = mul_div_ceil(xtoken_amount, refund, amount);
+        self.total_supply = self
+            .total_supply
+            .checked_add(refund)
+            .unwrap_or_else(|| env::panic_str("Add will overflow"));
+        self.total_xtoken_supply = self
+            .total_xtoken_supply
+            .checked_add(xtoken_refund)
+            .unwrap_or_else(|| env::panic_str("Add will overflow"));
+        if self.xtoken_cost.convert_to_amount(self.total_xtoken_supply) > self.staked_supply() {
+            self.xtoken_cost = XTokenCost::new(self.staked_supply(), self.total_xtoken_supply);
+        }
+        self.migrate_reward_weight(account_id);
+        // The account could be unregistered in between
+        let account_xtokens_amount = self.accounts.get(account_id).unwrap_or_default();
+        self.accounts.insert(
+            account_id,
+            &(account_xtokens_amount
+                .checked_add(xtoken_refund)
+                .unwrap_or_else(|| env::panic_str("Add will overflow"))),
+        );
+
+        near_contract_standards::fungible_token::events::FtMint {
+            owner_id: account_id,
+            amount: &xtoken_refund.into(),
+            memo: Some("Unstake rollback"),
+        }
+        .emit();
+        events::UnstakeRollback {
+            account_id,
+            amount: refund.into(),
+            xtoken_amount: xtoken_refund.into(),
+        }
+        .emit();
+
+        self.update_reward_weight(account_id);
+        xtoken_refund
+    }
+
+    /// Tokens backing the xtokens, the stake dust is not shared by them.
+    pub fn staked_supply(&self) -> Balance {
+        self.total_supply
+            .checked_sub(self.dust)
+            .unwrap_or_else(|| env::panic_str("Sub will overflow"))
+    }
+
     pub fn internal_register_account(&mut self, account_id: &AccountId) {
         if self.accounts.insert(account_id, &0).is_some() {
             env::panic_str("The account is already registered");
@@ -214,7 +446,8 @@ impl Contract {
     pub fn add_to_pool_internal(&mut self, amount: Balance) {
         self.total_supply += amount;
         if self.total_xtoken_supply != 0 {
-            self.xtoken_cost = XTokenCost::new(self.total_supply, self.total_xtoken_supply);
+            self.xtoken_cost = XTokenCost::new(self.staked_supply(), self.total_xtoken_supply);
         }
+        self.record_price_snapshot();
     }
 }
@@ -0,0 +1,104 @@
+use crate::*;
+use near_contract_standards::upgrade::Ownable;
+use near_sdk::serde::{Deserialize, Serialize};
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakeLimits {
+    /// Minimum amount of tokens in one stake
+    pub min_stake: U128,
+    /// Maximum amount of tokens staked by one account, unlimited if `None`
+    pub max_stake_per_account: Option<U128>,
+    /// Maximum amount of tokens in the pool, unlimited if `None`
+    pub max_total_stake: Option<U128>,
+    /// Minimum amount of tokens in one unstake, unless it unstakes the whole balance,
+    /// unlimited if `None`. Every unstake creates a lockup paid by this contract.
+    pub min_unstake: Option<U128>,
+}
+
+impl StakeLimits {
+    pub fn assert_valid(&self) {
+        for max in [self.max_stake_per_account, self.max_total_stake]
+            .into_iter()
+            .flatten()
+        {
+            require!(
+                self.min_stake.0 <= max.0,
+                "The min stake should not exceed the max stakes"
+            );
+        }
+    }
+}
+
+impl Default for StakeLimits {
+    fn default() -> Self {
+        Self {
+            min_stake: U128(0),
+            max_stake_per_account: None,
+            max_total_stake: None,
+            min_unstake: None,
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Caller must be the owner.
+    #[payable]
+    pub fn set_stake_limits(&mut self, limits: StakeLimits) {
+        assert_one_yocto();
+        self.assert_owner();
+        limits.assert_valid();
+        self.stake_limits = limits;
+    }
+
+    pub fn get_stake_limits(&self) -> &StakeLimits {
+        &self.stake_limits
+    }
+}
+
+impl Contract {
+    /// Part of `amount` that can be staked by `account_id` within the limits.
+    /// The rest should be refunded.
+    pub fn stake_limited_amount(&self, account_id: &AccountId, amount: Balance) -> Balance {
+        let mut limited = amount;
+        if let Some(max_stake_per_account) = self.stake_limits.max_stake_per_account {
+            let staked = self
+                .xtoken_cost
+                .convert_to_amount(self.accounts.get(account_id).unwrap_or_default());
+            limited = limited.min(max_stake_per_account.0.saturating_sub(staked));
+        }
+        if let Some(max_total_stake) = self.stake_limits.max_total_stake {
+            limited = limited.min(max_total_stake.0.saturating_sub(self.total_supply));
+        }
+        require!(limited > 0, "Stake limit exceeded");
+        // The staked part must meet the minimum, not the deposit
+        require!(
+            limited >= self.stake_limits.min_stake.0,
+            format!(
+                "The amount should be at least {}",
+                self.stake_limits.min_stake.0
+            )
+        );
+        limited
+    }
+
+    /// Panics if `amount` unstaked for `xtoken_amount` is below the minimum
+    /// and the account keeps some xtokens.
+    pub fn assert_min_unstake(
+        &self,
+        account_id: &AccountId,
+        xtoken_amount: Balance,
+        amount: Balance,
+    ) {
+        if let Some(min_unstake) = self.stake_limits.min_unstake {
+            require!(
+                amount >= min_unstake.0
+                    || xtoken_amount == self.accounts.get(account_id).unwrap_or_default(),
+                format!("The unstaked amount should be at least {}", min_unstake.0)
+            );
+        }
+    }
+}
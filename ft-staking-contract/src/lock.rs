@@ -0,0 +1,483 @@
+use crate::{xtoken::mul_div, *};
+use near_sdk::{
+    serde::{Deserialize, Serialize},
+    Timestamp,
+};
+
+pub const MIN_LOCK_WEEKS: u32 = 1;
+pub const MAX_LOCK_WEEKS: u32 = 52;
+/// Multipliers and penalties are expressed in basis points,
+/// `MULTIPLIER_DENOMINATOR` stands for `1x`.
+pub const MULTIPLIER_DENOMINATOR: u128 = 10_000;
+/// Reward multiplier of a position with `MAX_LOCK_WEEKS` left until unlock.
+/// Boosts the partner reward tokens only, LIS added to the pool
+/// raises the price of all the xtokens equally.
+pub const MAX_LOCK_MULTIPLIER: u128 = 25_000;
+/// Part of the locked xtokens burned on early unlock of a position
+/// with `MAX_LOCK_WEEKS` left until unlock.
+pub const MAX_EARLY_UNLOCK_PENALTY: u128 = 5_000;
+
+/// Xtokens of an account that can't be unstaked until `unlock_on`.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StakeLock {
+    pub xtoken_amount: Balance,
+    pub locked_on: Timestamp,
+    pub unlock_on: Timestamp,
+}
+
+/// Reward weight of an account stored on its last checkpoint.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RewardWeight {
+    /// Weight counted in the total reward weight
+    pub weight: Balance,
+    /// Weight with the lock multiplier of `1x`
+    pub unboosted_weight: Balance,
+    /// The lock boost ends at the start of this week
+    pub boost_week: u64,
+}
+
+impl RewardWeight {
+    pub fn unboosted(weight: Balance) -> Self {
+        Self {
+            weight,
+            unboosted_weight: weight,
+            boost_week: 0,
+        }
+    }
+
+    /// Part of the weight added by the lock multiplier.
+    pub fn boost(&self) -> Balance {
+        self.weight.saturating_sub(self.unboosted_weight)
+    }
+
+    pub fn is_boost_ended(&self) -> bool {
+        self.boost() > 0 && current_week() >= self.boost_week
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakeLockView {
+    pub xtoken_amount: U128,
+    pub locked_on: U64,
+    pub unlock_on: U64,
+    /// Current reward multiplier in basis points
+    pub multiplier: u32,
+    pub voting_power: U128,
+}
+
+impl From<StakeLock> for StakeLockView {
+    fn from(lock: StakeLock) -> Self {
+        Self {
+            xtoken_amount: lock.xtoken_amount.into(),
+            locked_on: lock.locked_on.into(),
+            unlock_on: lock.unlock_on.into(),
+            multiplier: lock.multiplier() as u32,
+            voting_power: lock.voting_power().into(),
+        }
+    }
+}
+
+impl StakeLock {
+    pub fn new(xtoken_amount: Balance, weeks: u32) -> Self {
+        Self {
+            xtoken_amount,
+            locked_on: env::block_timestamp(),
+            unlock_on: unlock_on(weeks),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.unlock_on > env::block_timestamp()
+    }
+
+    /// Xtokens that are still locked at the current block.
+    pub fn locked_amount(&self) -> Balance {
+        if self.is_active() {
+            self.xtoken_amount
+        } else {
+            0
+        }
+    }
+
+    /// Decays linearly from `MAX_LOCK_MULTIPLIER` to `1x`
+    /// as the lock runs down.
+    pub fn multiplier(&self) -> u128 {
+        MULTIPLIER_DENOMINATOR
+            + mul_div(
+                MAX_LOCK_MULTIPLIER - MULTIPLIER_DENOMINATOR,
+                self.remaining().into(),
+                max_lock_duration().into(),
+            )
+    }
+
+    /// Locked xtokens weighted by the time left until unlock,
+    /// equals to `xtoken_amount` for a lock with `MAX_LOCK_WEEKS` left.
+    pub fn voting_power(&self) -> Balance {
+        mul_div(
+            self.xtoken_amount,
+            self.remaining().into(),
+            max_lock_duration().into(),
+        )
+    }
+
+    /// The lock boost ends at the start of the week `unlock_on` falls in,
+    /// so the ending boosts can be subtracted from the total reward weight weekly.
+    pub fn boost_week(&self) -> u64 {
+        self.unlock_on / WEEK.0
+    }
+
+    /// Locked xtokens with the multiplier applied until the boost ends.
+    pub fn reward_weight(&self) -> Balance {
+        if current_week() < self.boost_week() {
+            mul_div(
+                self.locked_amount(),
+                self.multiplier(),
+                MULTIPLIER_DENOMINATOR,
+            )
+        } else {
+            self.locked_amount()
+        }
+    }
+
+    /// Xtokens burned if the lock is released right now.
+    pub fn early_unlock_penalty(&self) -> Balance {
+        mul_div(
+            mul_div(
+                self.xtoken_amount,
+                self.remaining().into(),
+                max_lock_duration().into(),
+            ),
+            MAX_EARLY_UNLOCK_PENALTY,
+            MULTIPLIER_DENOMINATOR,
+        )
+    }
+
+    fn remaining(&self) -> u64 {
+        self.unlock_on.saturating_sub(env::block_timestamp())
+    }
+}
+
+pub fn assert_lock_weeks(weeks: u32) {
+    require!(
+        (MIN_LOCK_WEEKS..=MAX_LOCK_WEEKS).contains(&weeks),
+        format!("Lock period should be from {MIN_LOCK_WEEKS} to {MAX_LOCK_WEEKS} weeks")
+    );
+}
+
+fn unlock_on(weeks: u32) -> Timestamp {
+    env::block_timestamp()
+        .checked_add(
+            WEEK.0
+                .checked_mul(weeks.into())
+                .unwrap_or_else(|| env::panic_str("Mul will overflow")),
+        )
+        .unwrap_or_else(|| env::panic_str("Add will overflow"))
+}
+
+pub fn current_week() -> u64 {
+    env::block_timestamp() / WEEK.0
+}
+
+fn max_lock_duration() -> u64 {
+    WEEK.0 * MAX_LOCK_WEEKS as u64
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Prolong the lock of the caller to `weeks` from now.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Caller must have an active lock that ends before the new unlock time.
+    #[payable]
+    pub fn extend_lock(&mut self, weeks: u32) {
+        assert_one_yocto();
+        self.extend_lock_internal(&env::predecessor_account_id(), 0, weeks);
+    }
+
+    /// Release the lock of the caller before `unlock_on`.
+    /// Penalty is burned from the locked xtokens and stays in the pool.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Caller must have an active lock.
+    #[payable]
+    pub fn unlock_early(&mut self) -> U128 {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let lock = self
+            .locks
+            .get(&account_id)
+            .filter(StakeLock::is_active)
+            .unwrap_or_else(|| env::panic_str("No active lock found"));
+        let penalty = lock.early_unlock_penalty();
+        self.locks.remove(&account_id);
+
+        if penalty > 0 {
+            self.total_xtoken_supply = self
+                .total_xtoken_supply
+                .checked_sub(penalty)
+                .unwrap_or_else(|| env::panic_str("Sub will overflow"));
+            self.migrate_reward_weight(&account_id);
+            let account_xtokens_amount = self.accounts.get(&account_id).unwrap_or_default();
+            self.accounts.insert(
+                &account_id,
+                &(account_xtokens_amount
+                    .checked_sub(penalty)
+                    .unwrap_or_else(|| env::panic_str("Sub will overflow"))),
+            );
+            // Burned xtokens leave their tokens in the pool
+            self.xtoken_cost = XTokenCost::new(self.staked_supply(), self.total_xtoken_supply);
+
+            near_contract_standards::fungible_token::events::FtBurn {
+                owner_id: &account_id,
+                amount: &penalty.into(),
+                memo: Some("Early unlock penalty"),
+            }
+            .emit();
+        }
+        self.update_reward_weight(&account_id);
+
+        penalty.into()
+    }
+
+    /// Recalculate the stored reward weight of `account_id`,
+    /// so the decay of its lock multiplier is taken into account.
+    /// The lock boost is subtracted from the stored and the total weights
+    /// at the start of the week of `unlock_on` without a checkpoint.
+    pub fn checkpoint(&mut self, account_id: AccountId) -> U128 {
+        self.assert_register(&account_id);
+        self.update_reward_weight(&account_id).into()
+    }
+
+    pub fn get_stake_lock(&self, account_id: AccountId) -> Option<StakeLockView> {
+        self.locks.get(&account_id).map(Into::into)
+    }
+
+    /// Reward weight of `account_id` at the current block.
+    pub fn get_reward_weight(&self, account_id: AccountId) -> U128 {
+        self.reward_weight(&account_id).into()
+    }
+
+    /// Sum of the reward weights stored on the last checkpoint of each account.
+    pub fn get_total_reward_weight(&self) -> U128 {
+        self.total_reward_weight.into()
+    }
+
+    pub fn get_voting_power(&self, account_id: AccountId) -> U128 {
+        self.locks
+            .get(&account_id)
+            .map(|lock| lock.voting_power())
+            .unwrap_or_default()
+            .into()
+    }
+}
+
+impl Contract {
+    pub fn lock_internal(&mut self, account_id: &AccountId, xtoken_amount: Balance, weeks: u32) {
+        assert_lock_weeks(weeks);
+        require!(
+            !self
+                .locks
+                .get(account_id)
+                .map(|lock| lock.is_active())
+                .unwrap_or_default(),
+            "The account already has an active lock"
+        );
+        self.locks
+            .insert(account_id, &StakeLock::new(xtoken_amount, weeks));
+        self.update_reward_weight(account_id);
+    }
+
+    pub fn extend_lock_internal(
+        &mut self,
+        account_id: &AccountId,
+        xtoken_amount: Balance,
+        weeks: u32,
+    ) {
+        assert_lock_weeks(weeks);
+        let mut lock = self
+            .locks
+            .get(account_id)
+            .filter(StakeLock::is_active)
+            .unwrap_or_else(|| env::panic_str("No active lock found"));
+        let unlock_on = unlock_on(weeks);
+        require!(unlock_on >= lock.unlock_on, "The lock can't be shortened");
+        lock.unlock_on = unlock_on;
+        lock.xtoken_amount = lock
+            .xtoken_amount
+            .checked_add(xtoken_amount)
+            .unwrap_or_else(|| env::panic_str("Add will overflow"));
+        self.locks.insert(account_id, &lock);
+        self.update_reward_weight(account_id);
+    }
+
+    /// Xtokens of `account_id` that can be unstaked right now.
+    pub fn unlocked_balance(&self, account_id: &AccountId) -> Balance {
+        let locked = self
+            .locks
+            .get(account_id)
+            .map(|lock| lock.locked_amount())
+            .unwrap_or_default();
+        self.accounts
+            .get(account_id)
+            .unwrap_or_default()
+            .saturating_sub(locked)
+    }
+
+    /// Staked xtokens with the lock and NFT multipliers applied.
+    pub fn reward_weight(&self, account_id: &AccountId) -> Balance {
+        let lock_weight = self
+            .locks
+            .get(account_id)
+            .map(|lock| lock.reward_weight())
+            .unwrap_or_default();
+        mul_div(
+            self.unlocked_balance(account_id)
+                .checked_add(lock_weight)
+                .unwrap_or_else(|| env::panic_str("Add will overflow")),
+            self.nft_multiplier(account_id),
+            MULTIPLIER_DENOMINATOR,
+        )
+    }
+
+    /// Store the current reward weight of `account_id`
+    /// and adjust the total reward weight.
+    /// Rewards accrued by the previous weight are settled first.
+    pub fn update_reward_weight(&mut self, account_id: &AccountId) -> Balance {
+        self.settle_rewards(account_id);
+        let weight = self.reward_weight(account_id);
+        let old_weight = self.stored_reward_weight(account_id);
+        self.remove_boost_end(&old_weight);
+        self.total_reward_weight = self
+            .total_reward_weight
+            .checked_sub(old_weight.weight)
+            .unwrap_or_else(|| env::panic_str("Sub will overflow"))
+            .checked_add(weight)
+            .unwrap_or_else(|| env::panic_str("Add will overflow"));
+        // Missing weight of an account with xtokens stands for the balance
+        let balance = self.accounts.get(account_id).unwrap_or_default();
+        if balance > 0 {
+            let unboosted_weight = mul_div(
+                balance,
+                self.nft_multiplier(account_id),
+                MULTIPLIER_DENOMINATOR,
+            );
+            let boost_week = self
+                .locks
+                .get(account_id)
+                .filter(StakeLock::is_active)
+                .map(|lock| lock.boost_week())
+                .unwrap_or_default();
+            let reward_weight = RewardWeight {
+                weight,
+                unboosted_weight,
+                boost_week,
+            };
+            self.add_boost_end(&reward_weight);
+            self.reward_weights.insert(account_id, &reward_weight);
+        } else {
+            self.reward_weights.remove(account_id);
+        }
+        weight
+    }
+
+    /// Reward weight of `account_id` on the last checkpoint.
+    /// Accounts staked before the reward weights have none stored,
+    /// their weight is the xtoken balance.
+    pub fn stored_reward_weight(&self, account_id: &AccountId) -> RewardWeight {
+        self.reward_weights.get(account_id).unwrap_or_else(|| {
+            RewardWeight::unboosted(self.accounts.get(account_id).unwrap_or_default())
+        })
+    }
+
+    /// Store the xtoken balance as the reward weight of `account_id`
+    /// if it has none stored, so the fallback doesn't read the changed balance.
+    /// Must be called before the xtoken balance changes.
+    pub fn migrate_reward_weight(&mut self, account_id: &AccountId) {
+        if !self.reward_weights.contains_key(account_id) {
+            let balance = self.accounts.get(account_id).unwrap_or_default();
+            self.reward_weights
+                .insert(account_id, &RewardWeight::unboosted(balance));
+        }
+    }
+
+    /// Schedule the subtraction of the lock boost of `weight`
+    /// from the total reward weight at the start of its boost week.
+    fn add_boost_end(&mut self, weight: &RewardWeight) {
+        let boost = weight.boost();
+        if boost > 0 {
+            let boost_end = self
+                .boost_ends
+                .get(&weight.boost_week)
+                .unwrap_or_default()
+                .checked_add(boost)
+                .unwrap_or_else(|| env::panic_str("Add will overflow"));
+            self.boost_ends.insert(&weight.boost_week, &boost_end);
+        }
+    }
+
+    /// Cancel the scheduled subtraction of the lock boost of `weight`.
+    /// Must be called after the rewards of the weight are settled.
+    fn remove_boost_end(&mut self, weight: &RewardWeight) {
+        let boost = weight.boost();
+        if boost > 0 {
+            let boost_end = self
+                .boost_ends
+                .get(&weight.boost_week)
+                .unwrap_or_default()
+                .checked_sub(boost)
+                .unwrap_or_else(|| env::panic_str("Sub will overflow"));
+            if boost_end > 0 {
+                self.boost_ends.insert(&weight.boost_week, &boost_end);
+            } else {
+                self.boost_ends.remove(&weight.boost_week);
+            }
+        }
+    }
+
+    /// Subtract the lock boosts ended since the last call from the total reward weight
+    /// and record the reward accumulators at their end.
+    pub fn process_boost_ends(&mut self) {
+        let current_week = current_week();
+        while self.processed_week < current_week {
+            self.processed_week += 1;
+            if let Some(boost) = self.boost_ends.remove(&self.processed_week) {
+                self.total_reward_weight = self
+                    .total_reward_weight
+                    .checked_sub(boost)
+                    .unwrap_or_else(|| env::panic_str("Sub will overflow"));
+                let reward_tokens: Vec<_> = self.reward_tokens.iter().collect();
+                for (token_account_id, reward_token) in reward_tokens {
+                    let referral_per_weight = self
+                        .referral_per_weight
+                        .get(&token_account_id)
+                        .unwrap_or_default();
+                    self.boost_end_snapshots.insert(
+                        &(token_account_id, self.processed_week),
+                        &(reward_token.reward_per_weight, referral_per_weight),
+                    );
+                }
+            }
+        }
+    }
+
+    /// `reward_per_weight` and `referral_per_weight` of `token_account_id`
+    /// at the end of the lock boost of `weight`.
+    /// None if the boost hasn't ended or no rewards were added since its end.
+    pub fn boost_end_snapshot(
+        &self,
+        weight: &RewardWeight,
+        token_account_id: &AccountId,
+    ) -> Option<(u128, u128)> {
+        if !weight.is_boost_ended() || weight.boost_week > self.processed_week {
+            return None;
+        }
+        Some(
+            self.boost_end_snapshots
+                .get(&(token_account_id.clone(), weight.boost_week))
+                .unwrap_or_default(),
+        )
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{lock::MULTIPLIER_DENOMINATOR, *};
+use near_contract_standards::{
+    non_fungible_token::{core::NonFungibleTokenReceiver, TokenId},
+    upgrade::Ownable,
+};
+use near_sdk::{
+    ext_contract, is_promise_success,
+    serde::{Deserialize, Serialize},
+};
+
+/// Boost multipliers can't be greater than `3x`
+pub const MAX_NFT_MULTIPLIER: u32 = 30_000;
+
+#[ext_contract(ext_nft)]
+#[allow(dead_code)]
+trait NftContract {
+    fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    );
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakedNft {
+    pub nft_contract_id: AccountId,
+    pub token_id: TokenId,
+}
+
+impl StakedNft {
+    /// Series of the token is the part of `token_id` before `:`,
+    /// or the whole `token_id` if it has no series delimiter.
+    pub fn series(&self) -> String {
+        nft_series(&self.token_id)
+    }
+}
+
+pub fn nft_series(token_id: &str) -> String {
+    token_id.split(':').next().unwrap_or_default().to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftMultiplierView {
+    pub nft_contract_id: AccountId,
+    pub series: String,
+    /// Reward weight multiplier in basis points
+    pub multiplier: u32,
+}
+
+#[near_bindgen]
+impl NonFungibleTokenReceiver for Contract {
+    /// Stake the NFT to boost the reward weight of `previous_owner_id`.
+    /// Only one NFT per account can be staked.
+    fn nft_on_transfer(
+        &mut self,
+        #[allow(unused_variables)] sender_id: AccountId,
+        previous_owner_id: AccountId,
+        token_id: TokenId,
+        #[allow(unused_variables)] msg: String,
+    ) -> PromiseOrValue<bool> {
+        self.assert_not_emergency();
+        let nft_contract_id = env::predecessor_account_id();
+        require!(
+            self.nft_whitelist.contains(&nft_contract_id),
+            "Invalid NFT contract"
+        );
+        self.assert_register(&previous_owner_id);
+        require!(
+            self.nft_multipliers
+                .get(&(nft_contract_id.clone(), nft_series(&token_id)))
+                .is_some(),
+            "No boost for the NFT series"
+        );
+        require!(
+            self.staked_nfts.get(&previous_owner_id).is_none(),
+            "The account already has a staked NFT"
+        );
+        self.staked_nfts.insert(
+            &previous_owner_id,
+            &StakedNft {
+                nft_contract_id,
+                token_id,
+            },
+        );
+        self.update_reward_weight(&previous_owner_id);
+
+        PromiseOrValue::Value(false)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Return the staked NFT of the caller, the boost is removed.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Caller must have a staked NFT.
+    #[payable]
+    pub fn withdraw_nft(&mut self) -> Promise {
+        assert_one_yocto();
+        self.return_nft_internal(&env::predecessor_account_id())
+            .unwrap_or_else(|| env::panic_str("No staked NFT found"))
+    }
+
+    #[private]
+    pub fn withdraw_nft_callback(&mut self, account_id: AccountId, staked_nft: StakedNft) {
+        if is_promise_success() {
+            return;
+        }
+        // Rollback the boost if transfer fail
+        self.staked_nfts.insert(&account_id, &staked_nft);
+        self.update_reward_weight(&account_id);
+    }
+
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Caller must be the owner.
+    #[payable]
+    pub fn add_nft_contract(&mut self, nft_contract_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.nft_whitelist.insert(&nft_contract_id);
+    }
+
+    /// Already staked NFTs of the contract keep their boost until withdrawn.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Caller must be the owner.
+    #[payable]
+    pub fn remove_nft_contract(&mut self, nft_contract_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.nft_whitelist.remove(&nft_contract_id);
+    }
+
+    /// Set the reward weight multiplier of the NFT series in basis points,
+    /// the series gives no boost if `multiplier` is `None`.
+    /// Accounts get the new multiplier on their next checkpoint.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Caller must be the owner.
+    #[payable]
+    pub fn set_nft_multiplier(
+        &mut self,
+        nft_contract_id: AccountId,
+        series: String,
+        multiplier: Option<u32>,
+    ) {
+        assert_one_yocto();
+        self.assert_owner();
+        let key = (nft_contract_id, series);
+        match multiplier {
+            Some(multiplier) => {
+                require!(
+                    (MULTIPLIER_DENOMINATOR as u32..=MAX_NFT_MULTIPLIER).contains(&multiplier),
+                    format!(
+                        "The multiplier should be from {MULTIPLIER_DENOMINATOR} to {MAX_NFT_MULTIPLIER}"
+                    )
+                );
+                self.nft_multipliers.insert(&key, &multiplier);
+            }
+            None => {
+                self.nft_multipliers.remove(&key);
+            }
+        }
+    }
+
+    pub fn get_nft_whitelist(&self) -> Vec<AccountId> {
+        self.nft_whitelist.to_vec()
+    }
+
+    pub fn get_nft_multipliers(&self) -> Vec<NftMultiplierView> {
+        self.nft_multipliers
+            .iter()
+            .map(
+                |((nft_contract_id, series), multiplier)| NftMultiplierView {
+                    nft_contract_id,
+                    series,
+                    multiplier,
+                },
+            )
+            .collect()
+    }
+
+    pub fn get_staked_nft(&self, account_id: AccountId) -> Option<StakedNft> {
+        self.staked_nfts.get(&account_id)
+    }
+}
+
+impl Contract {
+    /// Reward weight multiplier of the staked NFT of `account_id` in basis points.
+    pub fn nft_multiplier(&self, account_id: &AccountId) -> u128 {
+        self.staked_nfts
+            .get(account_id)
+            .and_then(|staked_nft| {
+                self.nft_multipliers
+                    .get(&(staked_nft.nft_contract_id.clone(), staked_nft.series()))
+            })
+            .map(u128::from)
+            .unwrap_or(MULTIPLIER_DENOMINATOR)
+    }
+
+    /// Transfer the staked NFT back to `account_id` if any.
+    pub fn return_nft_internal(&mut self, account_id: &AccountId) -> Option<Promise> {
+        let staked_nft = self.staked_nfts.remove(account_id)?;
+        self.update_reward_weight(account_id);
+
+        Some(
+            ext_nft::ext(staked_nft.nft_contract_id.clone())
+                .with_static_gas(GAS_FOR_NFT_TRANSFER)
+                .with_attached_deposit(ONE_YOCTO)
+                .nft_transfer(account_id.clone(), staked_nft.token_id.clone(), None, None)
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(GAS_FOR_NFT_TRANSFER_CALLBACK)
+                        .withdraw_nft_callback(account_id.clone(), staked_nft),
+                ),
+        )
+    }
+}
@@ -0,0 +1,212 @@
+use crate::*;
+use common::events::{self, RealisEvent};
+use near_sdk::{
+    serde::{Deserialize, Serialize},
+    Timestamp,
+};
+
+/// Permission of an operator to unstake and restake xtokens of an account.
+/// Unstaked tokens always go to the lockup of the account.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OperatorGrant {
+    /// Xtokens the operator can still unstake or restake, unlimited if `None`
+    pub allowance: Option<Balance>,
+    /// The grant is not valid since this moment, never expires if `None`
+    pub expires_at: Option<Timestamp>,
+    /// Paid by the account for the storage of the grant, refunded on revoke
+    pub storage_deposit: Balance,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OperatorGrantView {
+    pub allowance: Option<U128>,
+    pub expires_at: Option<U64>,
+}
+
+impl From<OperatorGrant> for OperatorGrantView {
+    fn from(grant: OperatorGrant) -> Self {
+        Self {
+            allowance: grant.allowance.map(Into::into),
+            expires_at: grant.expires_at.map(Into::into),
+        }
+    }
+}
+
+impl OperatorGrant {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .map(|expires_at| expires_at <= env::block_timestamp())
+            .unwrap_or_default()
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Allow `operator_id` to unstake xtokens of the caller
+    /// and to restake for the caller with the `RestakeFor` message.
+    /// Replaces the previous grant of the operator if any.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit covering the storage of the grant.
+    ///   The rest of the deposit and the storage deposit of the replaced grant are refunded.
+    /// * Caller must be registered.
+    /// * `expires_at` must be in the future.
+    #[payable]
+    pub fn grant_operator(
+        &mut self,
+        operator_id: AccountId,
+        allowance: Option<U128>,
+        expires_at: Option<U64>,
+    ) {
+        let owner_id = env::predecessor_account_id();
+        self.assert_register(&owner_id);
+        require!(owner_id != operator_id, "Can't grant to self");
+        let mut grant = OperatorGrant {
+            allowance: allowance.map(|a| a.0),
+            expires_at: expires_at.map(|e| e.0),
+            storage_deposit: 0,
+        };
+        require!(!grant.is_expired(), "The grant is already expired");
+        let key = (owner_id.clone(), operator_id.clone());
+        let old_storage_deposit = self
+            .operators
+            .remove(&key)
+            .map(|grant| grant.storage_deposit)
+            .unwrap_or_default();
+        let initial_storage_usage = env::storage_usage();
+        self.operators.insert(&key, &grant);
+        grant.storage_deposit = Balance::from(
+            env::storage_usage()
+                .checked_sub(initial_storage_usage)
+                .unwrap_or_else(|| env::panic_str("Sub will overflow")),
+        ) * env::storage_byte_cost();
+        self.operators.insert(&key, &grant);
+
+        let refund = env::attached_deposit()
+            .checked_add(old_storage_deposit)
+            .unwrap_or_else(|| env::panic_str("Add will overflow"))
+            .checked_sub(grant.storage_deposit)
+            .unwrap_or_else(|| {
+                env::panic_str(
+                    format!(
+                        "The attached deposit is less than the storage cost {}",
+                        grant.storage_deposit
+                    )
+                    .as_str(),
+                )
+            });
+        if refund > 0 {
+            Promise::new(owner_id.clone()).transfer(refund);
+        }
+
+        events::OperatorGrant {
+            owner_id: &owner_id,
+            operator_id: &operator_id,
+            allowance,
+            expires_at,
+        }
+        .emit();
+    }
+
+    /// Refunds the storage deposit of the grant to the caller.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * `operator_id` must have a grant from the caller.
+    #[payable]
+    pub fn revoke_operator(&mut self, operator_id: AccountId) {
+        assert_one_yocto();
+        let owner_id = env::predecessor_account_id();
+        let grant = self
+            .operators
+            .remove(&(owner_id.clone(), operator_id.clone()))
+            .unwrap_or_else(|| env::panic_str("Operator not found"));
+        if grant.storage_deposit > 0 {
+            Promise::new(owner_id.clone()).transfer(grant.storage_deposit);
+        }
+
+        events::OperatorRevoke {
+            owner_id: &owner_id,
+            operator_id: &operator_id,
+        }
+        .emit();
+    }
+
+    /// Unstake xtokens of `account_id` by the operator.
+    /// Tokens are sent to the lockup of `account_id`.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Caller must have a valid grant from `account_id`.
+    /// * `xtoken_amount` must not exceed the allowance of the grant.
+    ///   The part refunded by the lockup is returned to the allowance.
+    #[payable]
+    pub fn unstake_for(&mut self, account_id: AccountId, xtoken_amount: Option<U128>) -> Promise {
+        assert_one_yocto();
+        let operator_id = env::predecessor_account_id();
+        let xtoken_amount = xtoken_amount
+            .map(|a| a.0)
+            .unwrap_or_else(|| self.unlocked_balance(&account_id));
+        require!(xtoken_amount > 0, "The xtoken_amount should not be zero");
+        self.use_operator_grant(&account_id, &operator_id, xtoken_amount);
+        self.unstake_internal(&account_id, xtoken_amount, Some(&operator_id))
+    }
+
+    pub fn get_operator_grant(
+        &self,
+        account_id: AccountId,
+        operator_id: AccountId,
+    ) -> Option<OperatorGrantView> {
+        self.operators
+            .get(&(account_id, operator_id))
+            .map(Into::into)
+    }
+}
+
+impl Contract {
+    /// Take `xtoken_amount` from the allowance of the grant of `operator_id`
+    /// from `account_id`, panics if the grant is missing or expired.
+    pub fn use_operator_grant(
+        &mut self,
+        account_id: &AccountId,
+        operator_id: &AccountId,
+        xtoken_amount: Balance,
+    ) {
+        let key = (account_id.clone(), operator_id.clone());
+        let mut grant = self
+            .operators
+            .get(&key)
+            .filter(|grant| !grant.is_expired())
+            .unwrap_or_else(|| env::panic_str("Not an operator of the account"));
+        if let Some(allowance) = grant.allowance {
+            grant.allowance = Some(
+                allowance
+                    .checked_sub(xtoken_amount)
+                    .unwrap_or_else(|| env::panic_str("Operator allowance exceeded")),
+            );
+            self.operators.insert(&key, &grant);
+        }
+    }
+
+    /// Return `xtoken_amount` to the allowance of the grant,
+    /// unless it is unlimited or has been revoked in between.
+    pub fn restore_operator_allowance(
+        &mut self,
+        account_id: &AccountId,
+        operator_id: &AccountId,
+        xtoken_amount: Balance,
+    ) {
+        let key = (account_id.clone(), operator_id.clone());
+        if let Some(mut grant) = self.operators.get(&key) {
+            if let Some(allowance) = grant.allowance {
+                grant.allowance = Some(
+                    allowance
+                        .checked_add(xtoken_amount)
+                        .unwrap_or_else(|| env::panic_str("Add will overflow")),
+                );
+                self.operators.insert(&key, &grant);
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::*;
+use near_contract_standards::upgrade::Ownable;
+use near_sdk::{
+    is_promise_success,
+    serde::{Deserialize, Serialize},
+};
+use std::collections::HashMap;
+
+/// Fees are expressed in basis points
+pub const REFERRAL_FEE_DENOMINATOR: u32 = 10_000;
+pub const MAX_REFERRAL_FEE: u32 = 5_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct ReferralReward {
+    /// Referral rewards that are not claimed yet
+    pub unclaimed: Balance,
+    /// All referral rewards earned in the token
+    pub total_earned: Balance,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReferralRewardView {
+    pub unclaimed: U128,
+    pub total_earned: U128,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReferralStatsView {
+    pub num_referees: u32,
+    /// Reward token account id -> Referral rewards
+    pub rewards: HashMap<AccountId, ReferralRewardView>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Claim the referral rewards of the caller in `token_account_id`.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Caller must have referral rewards in the given token.
+    #[payable]
+    pub fn claim_referral_reward(&mut self, token_account_id: AccountId) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let key = (account_id.clone(), token_account_id.clone());
+        let mut reward = self.referral_rewards.get(&key).unwrap_or_default();
+        let amount = reward.unclaimed;
+        require!(amount > 0, "No rewards found");
+        reward.unclaimed = 0;
+        self.referral_rewards.insert(&key, &reward);
+
+        ext_ft_core::ext(token_account_id.clone())
+            .with_static_gas(GAS_FOR_REWARD_TRANSFER)
+            .with_attached_deposit(ONE_YOCTO)
+            .ft_transfer(account_id.clone(), amount.into(), None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_REWARD_CALLBACK)
+                    .claim_referral_reward_callback(account_id, token_account_id, amount.into()),
+            )
+    }
+
+    #[private]
+    pub fn claim_referral_reward_callback(
+        &mut self,
+        account_id: AccountId,
+        token_account_id: AccountId,
+        amount: U128,
+    ) {
+        if is_promise_success() {
+            return;
+        }
+        // Rollback claimed rewards if transfer fail
+        let key = (account_id, token_account_id);
+        let mut reward = self.referral_rewards.get(&key).unwrap_or_default();
+        reward.unclaimed = reward
+            .unclaimed
+            .checked_add(amount.0)
+            .unwrap_or_else(|| env::panic_str("Add will overflow"));
+        self.referral_rewards.insert(&key, &reward);
+    }
+
+    /// Set the part of the referee rewards that goes to the referrer, in basis points.
+    /// The fee applies to the rewards distributed after the change.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Caller must be the owner.
+    #[payable]
+    pub fn set_referral_fee(&mut self, referral_fee: u32) {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(
+            referral_fee <= MAX_REFERRAL_FEE,
+            format!("The referral fee should not exceed {MAX_REFERRAL_FEE}")
+        );
+        self.referral_fee = referral_fee;
+    }
+
+    pub fn get_referral_fee(&self) -> u32 {
+        self.referral_fee
+    }
+
+    pub fn get_referrer(&self, account_id: AccountId) -> Option<AccountId> {
+        self.referrers.get(&account_id)
+    }
+
+    pub fn get_referral_stats(&self, account_id: AccountId) -> ReferralStatsView {
+        ReferralStatsView {
+            num_referees: self.referees_count.get(&account_id).unwrap_or_default(),
+            rewards: self
+                .reward_tokens
+                .keys()
+                .filter_map(|token_account_id| {
+                    let reward = self
+                        .referral_rewards
+                        .get(&(account_id.clone(), token_account_id.clone()))?;
+                    Some((
+                        token_account_id,
+                        ReferralRewardView {
+                            unclaimed: reward.unclaimed.into(),
+                            total_earned: reward.total_earned.into(),
+                        },
+                    ))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Contract {
+    /// Set `referrer_id` as the referrer of `account_id` if it has none.
+    /// The referrer can't be changed later.
+    pub fn set_referrer_internal(&mut self, account_id: &AccountId, referrer_id: &AccountId) {
+        require!(account_id != referrer_id, "Can't refer self");
+        self.assert_register(referrer_id);
+        if self.referrers.get(account_id).is_some() {
+            return;
+        }
+        // The referrer takes no cut of the rewards distributed before
+        self.settle_rewards(account_id);
+        self.referrers.insert(account_id, referrer_id);
+        for token_account_id in self.reward_tokens.keys() {
+            if let Some(referral_per_weight) = self.referral_per_weight.get(&token_account_id) {
+                self.referral_per_weight_paid.insert(
+                    &(account_id.clone(), token_account_id),
+                    &referral_per_weight,
+                );
+            }
+        }
+        let count = self.referees_count.get(referrer_id).unwrap_or_default();
+        self.referees_count.insert(
+            referrer_id,
+            &count
+                .checked_add(1)
+                .unwrap_or_else(|| env::panic_str("Add will overflow")),
+        );
+    }
+
+    /// Part of the distributed `amount` of rewards that goes to the referrers.
+    pub fn referral_cut(&self, amount: Balance) -> Balance {
+        mul_div(
+            amount,
+            self.referral_fee.into(),
+            REFERRAL_FEE_DENOMINATOR.into(),
+        )
+    }
+
+    pub fn add_referral_reward_internal(
+        &mut self,
+        referrer_id: &AccountId,
+        token_account_id: &AccountId,
+        amount: Balance,
+    ) {
+        let key = (referrer_id.clone(), token_account_id.clone());
+        let mut reward = self.referral_rewards.get(&key).unwrap_or_default();
+        reward.unclaimed = reward
+            .unclaimed
+            .checked_add(amount)
+            .unwrap_or_else(|| env::panic_str("Add will overflow"));
+        reward.total_earned = reward
+            .total_earned
+            .checked_add(amount)
+            .unwrap_or_else(|| env::panic_str("Add will overflow"));
+        self.referral_rewards.insert(&key, &reward);
+    }
+}
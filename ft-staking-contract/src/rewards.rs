@@ -0,0 +1,453 @@
+use crate::{xtoken::mul_div, *};
+use near_contract_standards::upgrade::Ownable;
+use near_sdk::{
+    is_promise_success,
+    serde::{Deserialize, Serialize},
+};
+use std::collections::HashMap;
+
+/// Precision of the reward per weight accumulators
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000_000_000_000_000;
+/// Max number of the reward tokens including the removed ones,
+/// the storage deposit of an account covers its rewards in each of them.
+pub const MAX_REWARD_TOKENS: u64 = 8;
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct RewardToken {
+    /// Accumulated amount of rewards per unit of the reward weight,
+    /// multiplied by `REWARD_PRECISION`
+    pub reward_per_weight: u128,
+    /// Whether the token can be deposited as a reward
+    pub is_active: bool,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct AccountReward {
+    /// Value of `RewardToken::reward_per_weight` on the last settlement
+    pub reward_per_weight_paid: u128,
+    /// Settled rewards that are not claimed yet
+    pub unclaimed: Balance,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RewardTokenView {
+    pub token_account_id: AccountId,
+    pub reward_per_weight: U128,
+    pub is_active: bool,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Claim all settled rewards of the caller in `token_account_id`.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Caller must have rewards in the given token.
+    #[payable]
+    pub fn claim_reward(&mut self, token_account_id: AccountId) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.settle_rewards(&account_id);
+
+        let key = (account_id.clone(), token_account_id.clone());
+        let mut reward = self
+            .account_rewards
+            .get(&key)
+            .unwrap_or_else(|| env::panic_str("No rewards found"));
+        let amount = reward.unclaimed;
+        require!(amount > 0, "No rewards found");
+        reward.unclaimed = 0;
+        self.account_rewards.insert(&key, &reward);
+
+        ext_ft_core::ext(token_account_id.clone())
+            .with_static_gas(GAS_FOR_REWARD_TRANSFER)
+            .with_attached_deposit(ONE_YOCTO)
+            .ft_transfer(account_id.clone(), amount.into(), None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_REWARD_CALLBACK)
+                    .claim_reward_callback(account_id, token_account_id, amount.into()),
+            )
+    }
+
+    #[private]
+    pub fn claim_reward_callback(
+        &mut self,
+        account_id: AccountId,
+        token_account_id: AccountId,
+        amount: U128,
+    ) {
+        if is_promise_success() {
+            return;
+        }
+        // Rollback claimed rewards if transfer fail
+        let key = (account_id, token_account_id);
+        let mut reward = self.account_rewards.get(&key).unwrap_or_default();
+        reward.unclaimed = reward
+            .unclaimed
+            .checked_add(amount.0)
+            .unwrap_or_else(|| env::panic_str("Add will overflow"));
+        self.account_rewards.insert(&key, &reward);
+    }
+
+    /// Allow `token_account_id` to be deposited as a reward with `"AddToPool"` message.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Caller must be the owner.
+    /// * A new token must not exceed `MAX_REWARD_TOKENS`.
+    #[payable]
+    pub fn add_reward_token(&mut self, token_account_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(
+            token_account_id != self.token_account_id,
+            "Staked token can't be a reward token"
+        );
+        let mut reward_token = self
+            .reward_tokens
+            .get(&token_account_id)
+            .unwrap_or_else(|| {
+                require!(
+                    self.reward_tokens.len() < MAX_REWARD_TOKENS,
+                    "Too many reward tokens"
+                );
+                RewardToken {
+                    reward_per_weight: 0,
+                    is_active: true,
+                }
+            });
+        reward_token.is_active = true;
+        self.reward_tokens.insert(&token_account_id, &reward_token);
+    }
+
+    /// Stop accepting deposits of `token_account_id`.
+    /// Already distributed rewards stay claimable.
+    ///
+    /// Requirements
+    /// * Caller of the method must attach a deposit of 1 yoctoⓃ for security purposes.
+    /// * Caller must be the owner.
+    #[payable]
+    pub fn remove_reward_token(&mut self, token_account_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+        let mut reward_token = self
+            .reward_tokens
+            .get(&token_account_id)
+            .unwrap_or_else(|| env::panic_str("Not a reward token"));
+        reward_token.is_active = false;
+        self.reward_tokens.insert(&token_account_id, &reward_token);
+    }
+
+    pub fn get_reward_tokens(&self) -> Vec<RewardTokenView> {
+        self.reward_tokens
+            .iter()
+            .map(|(token_account_id, reward_token)| RewardTokenView {
+                token_account_id,
+                reward_per_weight: reward_token.reward_per_weight.into(),
+                is_active: reward_token.is_active,
+            })
+            .collect()
+    }
+
+    /// Rewards of `account_id` that can be claimed, by reward token.
+    pub fn get_unclaimed_rewards(&self, account_id: AccountId) -> HashMap<AccountId, U128> {
+        let weight = self.stored_reward_weight(&account_id);
+        let has_referrer = self.referrers.contains_key(&account_id);
+        self.reward_tokens
+            .iter()
+            .filter_map(|(token_account_id, reward_token)| {
+                let reward = self
+                    .account_rewards
+                    .get(&(account_id.clone(), token_account_id.clone()))
+                    .unwrap_or_default();
+                let boost_end = self.boost_end_snapshot(&weight, &token_account_id);
+                let mut accrued = weight.accrued_reward(
+                    reward_token.reward_per_weight,
+                    reward.reward_per_weight_paid,
+                    boost_end.map(|(reward_per_weight, _)| reward_per_weight),
+                );
+                if has_referrer {
+                    accrued -= weight.accrued_reward(
+                        self.referral_per_weight
+                            .get(&token_account_id)
+                            .unwrap_or_default(),
+                        self.referral_per_weight_paid
+                            .get(&(account_id.clone(), token_account_id.clone()))
+                            .unwrap_or_default(),
+                        boost_end.map(|(_, referral_per_weight)| referral_per_weight),
+                    );
+                }
+                let amount = reward.unclaimed + accrued;
+                (amount > 0).then(|| (token_account_id, amount.into()))
+            })
+            .collect()
+    }
+}
+
+impl Contract {
+    /// Distribute `amount` of `token_account_id` among all stakers by their reward weight.
+    /// Returns the amount that can't be distributed.
+    pub fn add_reward_internal(
+        &mut self,
+        token_account_id: &AccountId,
+        amount: Balance,
+    ) -> Balance {
+        let mut reward_token = self
+            .reward_tokens
+            .get(token_account_id)
+            .filter(|reward_token| reward_token.is_active)
+            .unwrap_or_else(|| env::panic_str("Invalid token ID"));
+        self.process_boost_ends();
+        if self.total_reward_weight == 0 {
+            return amount;
+        }
+        reward_token.reward_per_weight = reward_token
+            .reward_per_weight
+            .checked_add(mul_div(amount, REWARD_PRECISION, self.total_reward_weight))
+            .unwrap_or_else(|| env::panic_str("Add will overflow"));
+        self.reward_tokens.insert(token_account_id, &reward_token);
+
+        // The referral cut is fixed by the fee at the time of the distribution
+        let referral_cut = self.referral_cut(amount);
+        if referral_cut > 0 {
+            let referral_per_weight = self
+                .referral_per_weight
+                .get(token_account_id)
+                .unwrap_or_default()
+                .checked_add(mul_div(
+                    referral_cut,
+                    REWARD_PRECISION,
+                    self.total_reward_weight,
+                ))
+                .unwrap_or_else(|| env::panic_str("Add will overflow"));
+            self.referral_per_weight
+                .insert(token_account_id, &referral_per_weight);
+        }
+        0
+    }
+
+    /// Move rewards accrued by the stored reward weight of `account_id` to unclaimed.
+    /// Must be called before the stored reward weight changes.
+    /// Rewards added after the end of the lock boost are settled by the unboosted weight,
+    /// which replaces the stored one.
+    pub fn settle_rewards(&mut self, account_id: &AccountId) {
+        self.process_boost_ends();
+        let weight = self.stored_reward_weight(account_id);
+        let referrer_id = self.referrers.get(account_id);
+        let reward_tokens: Vec<_> = self.reward_tokens.iter().collect();
+        for (token_account_id, reward_token) in reward_tokens {
+            let key = (account_id.clone(), token_account_id.clone());
+            let mut reward = self.account_rewards.get(&key).unwrap_or_default();
+            if reward.reward_per_weight_paid == reward_token.reward_per_weight {
+                continue;
+            }
+            let boost_end = self.boost_end_snapshot(&weight, &token_account_id);
+            let accrued = weight.accrued_reward(
+                reward_token.reward_per_weight,
+                reward.reward_per_weight_paid,
+                boost_end.map(|(reward_per_weight, _)| reward_per_weight),
+            );
+            // Referrer takes a cut of the rewards distributed since the last settlement
+            let referral_cut = match &referrer_id {
+                Some(referrer_id) => {
+                    let referral_per_weight = self
+                        .referral_per_weight
+                        .get(&token_account_id)
+                        .unwrap_or_default();
+                    let referral_cut = weight.accrued_reward(
+                        referral_per_weight,
+                        self.referral_per_weight_paid.get(&key).unwrap_or_default(),
+                        boost_end.map(|(_, referral_per_weight)| referral_per_weight),
+                    );
+                    self.referral_per_weight_paid
+                        .insert(&key, &referral_per_weight);
+                    if referral_cut > 0 {
+                        self.add_referral_reward_internal(
+                            referrer_id,
+                            &token_account_id,
+                            referral_cut,
+                        );
+                    }
+                    referral_cut
+                }
+                None => 0,
+            };
+            reward.unclaimed = reward
+                .unclaimed
+                .checked_add(accrued - referral_cut)
+                .unwrap_or_else(|| env::panic_str("Add will overflow"));
+            reward.reward_per_weight_paid = reward_token.reward_per_weight;
+            self.account_rewards.insert(&key, &reward);
+        }
+        if weight.is_boost_ended() {
+            self.reward_weights.insert(
+                account_id,
+                &RewardWeight::unboosted(weight.unboosted_weight),
+            );
+        }
+    }
+}
+
+impl RewardWeight {
+    /// Rewards accrued between the `paid` and `current` values of a reward accumulator.
+    /// `boost_end` is the value at the end of the lock boost, if the boost has ended.
+    pub fn accrued_reward(&self, current: u128, paid: u128, boost_end: Option<u128>) -> Balance {
+        match boost_end {
+            Some(boost_end) => {
+                let boost_end = boost_end.clamp(paid, current);
+                accrued_reward(self.weight, boost_end, paid)
+                    + accrued_reward(self.unboosted_weight, current, boost_end)
+            }
+            None => accrued_reward(self.weight, current, paid),
+        }
+    }
+}
+
+fn accrued_reward(
+    weight: Balance,
+    reward_per_weight: u128,
+    reward_per_weight_paid: u128,
+) -> Balance {
+    mul_div(
+        weight,
+        reward_per_weight
+            .checked_sub(reward_per_weight_paid)
+            .unwrap_or_else(|| env::panic_str("Sub will overflow")),
+        REWARD_PRECISION,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::{test_utils::VMContextBuilder, testing_env};
+
+    fn account(name: &str) -> AccountId {
+        AccountId::new_unchecked(name.to_owned())
+    }
+
+    #[test]
+    fn staker_before_reward_weights_earns_rewards() {
+        let owner = account("owner.near");
+        let reward_token = account("reward.near");
+        let alice = account("alice.near");
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(owner.clone())
+            .attached_deposit(ONE_YOCTO)
+            .build());
+        let mut contract = Contract::new(None, account("token.near"), account("lockup.near"));
+        contract.add_reward_token(reward_token.clone());
+
+        // Staked before the reward weights, the total is seeded by the migration
+        contract.accounts.insert(&alice, &1_000);
+        contract.total_supply = 1_000;
+        contract.total_xtoken_supply = 1_000;
+        contract.xtoken_cost = XTokenCost::new(1_000, 1_000);
+        contract.total_reward_weight = contract.total_xtoken_supply;
+
+        assert_eq!(contract.add_reward_internal(&reward_token, 100), 0);
+        assert_eq!(
+            contract.get_unclaimed_rewards(alice.clone())[&reward_token].0,
+            100
+        );
+
+        // The rewards are settled by the old weight on the balance change
+        contract.stake_internal(&alice, 1_000);
+        assert_eq!(
+            contract.reward_weights.get(&alice),
+            Some(RewardWeight::unboosted(2_000))
+        );
+        assert_eq!(contract.total_reward_weight, 2_000);
+        assert_eq!(
+            contract.get_unclaimed_rewards(alice.clone())[&reward_token].0,
+            100
+        );
+
+        assert_eq!(contract.add_reward_internal(&reward_token, 100), 0);
+        assert_eq!(contract.get_unclaimed_rewards(alice)[&reward_token].0, 200);
+    }
+
+    #[test]
+    fn first_stake_adds_reward_weight() {
+        let alice = account("alice.near");
+        testing_env!(VMContextBuilder::new().build());
+        let mut contract = Contract::new(None, account("token.near"), account("lockup.near"));
+        contract.internal_register_account(&alice);
+
+        let xtokens_amount = contract.stake_internal(&alice, 1_000);
+        assert_eq!(
+            contract.reward_weights.get(&alice),
+            Some(RewardWeight::unboosted(xtokens_amount))
+        );
+        assert_eq!(contract.total_reward_weight, xtokens_amount);
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many reward tokens")]
+    fn reward_tokens_are_bounded() {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account("owner.near"))
+            .attached_deposit(ONE_YOCTO)
+            .build());
+        let mut contract = Contract::new(None, account("token.near"), account("lockup.near"));
+        for i in 0..MAX_REWARD_TOKENS {
+            contract.add_reward_token(account(&format!("reward{i}.near")));
+        }
+        // Re-adding a known token is not limited
+        contract.remove_reward_token(account("reward0.near"));
+        contract.add_reward_token(account("reward0.near"));
+
+        contract.add_reward_token(account("reward.near"));
+    }
+
+    #[test]
+    fn lock_boost_ends_at_unlock() {
+        let owner = account("owner.near");
+        let reward_token = account("reward.near");
+        let alice = account("alice.near");
+        let bob = account("bob.near");
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .predecessor_account_id(owner)
+            .attached_deposit(ONE_YOCTO)
+            .build());
+        let mut contract = Contract::new(None, account("token.near"), account("lockup.near"));
+        contract.add_reward_token(reward_token.clone());
+        for account_id in [&alice, &bob] {
+            contract.internal_register_account(account_id);
+        }
+        let xtoken_amount = contract.stake_internal(&alice, 1_000);
+        contract.lock_internal(&alice, xtoken_amount, lock::MAX_LOCK_WEEKS);
+        contract.stake_internal(&bob, 1_000);
+        let total_reward_weight = contract.total_reward_weight;
+        assert_eq!(total_reward_weight, xtoken_amount * 7 / 2);
+
+        assert_eq!(contract.add_reward_internal(&reward_token, 3_500), 0);
+
+        // The boost ends for the total when the next reward is added, without an account action
+        testing_env!(context
+            .block_timestamp(WEEK.0 * lock::MAX_LOCK_WEEKS as u64 + 1)
+            .build());
+        assert_eq!(contract.total_reward_weight, total_reward_weight);
+        assert_eq!(contract.add_reward_internal(&reward_token, 3_500), 0);
+        assert_eq!(contract.total_reward_weight, xtoken_amount * 2);
+        // Rewards added after the unlock are split equally
+        assert_eq!(
+            contract.get_unclaimed_rewards(alice.clone())[&reward_token].0,
+            2_500 + 1_750
+        );
+
+        contract.checkpoint(alice.clone());
+        assert_eq!(contract.total_reward_weight, xtoken_amount * 2);
+        assert_eq!(
+            contract.get_unclaimed_rewards(alice)[&reward_token].0,
+            2_500 + 1_750
+        );
+        assert_eq!(
+            contract.get_unclaimed_rewards(bob)[&reward_token].0,
+            1_000 + 1_750
+        );
+    }
+}
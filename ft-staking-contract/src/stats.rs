@@ -0,0 +1,158 @@
+use crate::*;
+use near_sdk::{
+    serde::{Deserialize, Serialize},
+    Timestamp,
+};
+use primitive_types::U256;
+
+/// Amount of xtokens in one XLIS, the price is given for it
+pub const ONE_XTOKEN: Balance = 1_000_000_000_000;
+/// Capacity of the price snapshots ring buffer
+pub const MAX_PRICE_SNAPSHOTS: u64 = 52;
+pub const YEAR: U64 = U64(365 * DAY.0);
+/// APR is expressed in basis points
+pub const APR_DENOMINATOR: u128 = 10_000;
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct PriceSnapshot {
+    pub timestamp: Timestamp,
+    pub xtoken_cost: XTokenCost,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceSnapshotView {
+    pub timestamp: U64,
+    /// Tokens per `ONE_XTOKEN`
+    pub xtoken_price: U128,
+}
+
+impl From<PriceSnapshot> for PriceSnapshotView {
+    fn from(snapshot: PriceSnapshot) -> Self {
+        Self {
+            timestamp: snapshot.timestamp.into(),
+            xtoken_price: snapshot.xtoken_cost.convert_to_amount(ONE_XTOKEN).into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolInfoView {
+    pub token_account_id: AccountId,
+    pub lockup_account_id: AccountId,
+    pub total_supply: U128,
+    pub total_xtoken_supply: U128,
+    /// Tokens per `ONE_XTOKEN`
+    pub xtoken_price: U128,
+    pub total_reward_weight: U128,
+    /// Tokens left in the pool by rounding of the stakes
+    pub dust: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn get_pool_info(&self) -> PoolInfoView {
+        PoolInfoView {
+            token_account_id: self.token_account_id.clone(),
+            lockup_account_id: self.lockup_account_id.clone(),
+            total_supply: self.total_supply.into(),
+            total_xtoken_supply: self.total_xtoken_supply.into(),
+            xtoken_price: self.get_xtoken_price(),
+            total_reward_weight: self.total_reward_weight.into(),
+            dust: self.dust.into(),
+        }
+    }
+
+    /// Tokens per `ONE_XTOKEN` at the current xtoken cost.
+    pub fn get_xtoken_price(&self) -> U128 {
+        self.xtoken_cost.convert_to_amount(ONE_XTOKEN).into()
+    }
+
+    /// Tokens that `account_id` would get by unstaking all xtokens.
+    pub fn get_account_staked_lis(&self, account_id: AccountId) -> U128 {
+        self.xtoken_cost
+            .convert_to_amount(self.accounts.get(&account_id).unwrap_or_default())
+            .into()
+    }
+
+    /// Weekly price snapshots from the oldest to the newest.
+    pub fn get_price_snapshots(&self) -> Vec<PriceSnapshotView> {
+        self.price_snapshot_indexes()
+            .map(|index| self.price_snapshots.get(index).unwrap().into())
+            .collect()
+    }
+
+    /// Annualized growth of the xtoken price in basis points
+    /// since the oldest snapshot within the last `weeks`, all snapshots by default.
+    pub fn get_apr(&self, weeks: Option<u32>) -> u32 {
+        let from = weeks.map(|weeks| {
+            env::block_timestamp().saturating_sub(WEEK.0.saturating_mul(weeks.into()))
+        });
+        let snapshot = match self
+            .price_snapshot_indexes()
+            .map(|index| self.price_snapshots.get(index).unwrap())
+            .find(|snapshot| from.map(|from| snapshot.timestamp >= from).unwrap_or(true))
+        {
+            Some(snapshot) => snapshot,
+            None => return 0,
+        };
+        let elapsed = env::block_timestamp().saturating_sub(snapshot.timestamp);
+        if elapsed == 0 {
+            return 0;
+        }
+        // current / snapshot price = (amount * snapshot xtokens) / (xtokens * snapshot amount)
+        let current =
+            U256::from(self.xtoken_cost.amount) * U256::from(snapshot.xtoken_cost.xtokens_amount);
+        let previous =
+            U256::from(self.xtoken_cost.xtokens_amount) * U256::from(snapshot.xtoken_cost.amount);
+        if current <= previous {
+            return 0;
+        }
+        let apr = (current - previous) * U256::from(APR_DENOMINATOR) * U256::from(YEAR.0)
+            / (previous * U256::from(elapsed));
+        if apr > U256::from(u32::MAX) {
+            u32::MAX
+        } else {
+            apr.as_u32()
+        }
+    }
+}
+
+impl Contract {
+    /// Append the current xtoken cost to the ring buffer
+    /// if a week has passed since the newest snapshot.
+    pub fn record_price_snapshot(&mut self) {
+        let timestamp = env::block_timestamp();
+        if let Some(index) = self.num_price_snapshots.checked_sub(1) {
+            let last = self
+                .price_snapshots
+                .get(index % MAX_PRICE_SNAPSHOTS)
+                .unwrap();
+            if timestamp < last.timestamp.saturating_add(WEEK.0) {
+                return;
+            }
+        }
+        let snapshot = PriceSnapshot {
+            timestamp,
+            xtoken_cost: XTokenCost::new(self.xtoken_cost.amount, self.xtoken_cost.xtokens_amount),
+        };
+        if self.num_price_snapshots < MAX_PRICE_SNAPSHOTS {
+            self.price_snapshots.push(&snapshot);
+        } else {
+            self.price_snapshots
+                .replace(self.num_price_snapshots % MAX_PRICE_SNAPSHOTS, &snapshot);
+        }
+        self.num_price_snapshots += 1;
+    }
+
+    fn price_snapshot_indexes(&self) -> impl Iterator<Item = u64> {
+        let len = self.price_snapshots.len();
+        let oldest = if self.num_price_snapshots > MAX_PRICE_SNAPSHOTS {
+            self.num_price_snapshots % MAX_PRICE_SNAPSHOTS
+        } else {
+            0
+        };
+        (0..len).map(move |i| (oldest + i) % len)
+    }
+}
@@ -59,6 +59,9 @@ impl StorageManagement for Contract {
         assert_one_yocto();
         let account_id = env::predecessor_account_id();
         match self.accounts.get(&account_id) {
+            Some(0) if !self.get_unclaimed_rewards(account_id.clone()).is_empty() => {
+                env::panic_str("Can't unregister the account with unclaimed rewards")
+            }
             Some(0) => {
                 self.accounts.remove(&account_id);
                 Promise::new(account_id.clone()).transfer(
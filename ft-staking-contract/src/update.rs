@@ -1,6 +1,14 @@
 use crate::*;
+use common::{
+    events::{self, RealisEvent},
+    impl_staged_upgrade,
+    state_version::{migration_start, read_state, set_state_version, state_version, StateVersion},
+};
 use near_contract_standards::upgrade::Ownable;
 
+/// Version of the `Contract` layout
+pub const STATE_VERSION: StateVersion = 1;
+
 #[near_bindgen]
 impl Ownable for Contract {
     fn get_owner(&self) -> AccountId {
@@ -9,6 +17,11 @@ impl Ownable for Contract {
 
     fn set_owner(&mut self, owner: AccountId) {
         self.assert_owner();
+        events::OwnerChange {
+            old_owner_id: &self.owner_id,
+            new_owner_id: &owner,
+        }
+        .emit();
         self.owner_id = owner;
     }
 }
@@ -18,10 +31,25 @@ impl Contract {
     #[private]
     #[init(ignore_state)]
     pub fn update() -> Self {
-        env::state_read().unwrap_or_else(|| env::panic_str("Not initialized"))
+        let mut contract: Contract = match migration_start(STATE_VERSION) {
+            0 => read_state::<ContractV0>().into(),
+            1 => read_state::<Contract>(),
+            _ => env::panic_str("Unknown state version"),
+        };
+        // Per account records could be added since the last measurement
+        contract.measure_account_storage_usage();
+        set_state_version(STATE_VERSION);
+        contract
+    }
+
+    pub fn get_state_version(&self) -> StateVersion {
+        state_version()
     }
 }
 
+impl_staged_upgrade!(Contract);
+
+/// State layout before the versioning.
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct ContractV0 {
     pub owner_id: AccountId,
@@ -33,3 +61,42 @@ pub struct ContractV0 {
     xtoken_cost: XTokenCost,
     account_storage_usage: u64,
 }
+
+impl From<ContractV0> for Contract {
+    fn from(contract: ContractV0) -> Self {
+        Self {
+            owner_id: contract.owner_id,
+            token_account_id: contract.token_account_id,
+            lockup_account_id: contract.lockup_account_id,
+            accounts: contract.accounts,
+            total_supply: contract.total_supply,
+            total_xtoken_supply: contract.total_xtoken_supply,
+            xtoken_cost: contract.xtoken_cost,
+            account_storage_usage: contract.account_storage_usage,
+            locks: LookupMap::new(b"l"),
+            // The weights of the stakers are their balances until the first checkpoint
+            reward_weights: LookupMap::new(b"w"),
+            total_reward_weight: contract.total_xtoken_supply,
+            boost_ends: LookupMap::new(b"d"),
+            boost_end_snapshots: LookupMap::new(b"k"),
+            processed_week: lock::current_week(),
+            reward_tokens: UnorderedMap::new(b"r"),
+            account_rewards: LookupMap::new(b"c"),
+            operators: LookupMap::new(b"o"),
+            price_snapshots: Vector::new(b"p"),
+            num_price_snapshots: 0,
+            dust: 0,
+            stake_limits: StakeLimits::default(),
+            emergency_mode: false,
+            referrers: LookupMap::new(b"f"),
+            referees_count: LookupMap::new(b"n"),
+            referral_rewards: LookupMap::new(b"e"),
+            referral_fee: 0,
+            referral_per_weight: LookupMap::new(b"g"),
+            referral_per_weight_paid: LookupMap::new(b"h"),
+            nft_whitelist: UnorderedSet::new(b"t"),
+            nft_multipliers: UnorderedMap::new(b"m"),
+            staked_nfts: LookupMap::new(b"s"),
+        }
+    }
+}
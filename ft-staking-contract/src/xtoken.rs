@@ -1,3 +1,22 @@
+//! Share math of the staking pool.
+//!
+//! Stakers own xtokens, which are shares of the pool tokens.
+//! The price of the shares is kept as the `amount`/`xtokens_amount` ratio of `XTokenCost`.
+//!
+//! All conversions round down, so they are always in favor of the pool:
+//! * stake mints not more xtokens than the deposited tokens are worth,
+//! * unstake pays not more tokens than the burned xtokens are worth.
+//!
+//! The only exception is the rollback of an unstake, which restores xtokens
+//! rounded up and lowers the cost if `total_supply` doesn't cover them anymore.
+//!
+//! It keeps the invariant that `total_supply` covers the redeemable value
+//! of all xtokens. Tokens left in the pool by rounding on stake are tracked as dust
+//! and excluded when the cost is recalculated from the totals, so they never raise the price.
+//!
+//! The price itself is not rounded: the cost is the exact ratio of the totals,
+//! only the conversions round. This module doesn't change the precision of the price.
+//! Conversions with zero output are rejected, so they must be done before any state changes.
 use near_sdk::{
     borsh::{self, BorshDeserialize, BorshSerialize},
     env, Balance,
@@ -21,6 +40,15 @@ impl Default for XTokenCost {
     }
 }
 
+/// Xtokens minted for a deposit.
+#[derive(PartialEq, Eq, Debug)]
+pub struct StakeShares {
+    pub xtokens_amount: Balance,
+    /// Part of the deposit that is not backed by the minted xtokens, rounded down,
+    /// so the xtokens minted over several deposits are never worth more than their backing.
+    pub dust: Balance,
+}
+
 impl XTokenCost {
     pub fn new(amount: Balance, xtokens_amount: Balance) -> Self {
         Self {
@@ -29,21 +57,263 @@ impl XTokenCost {
         }
     }
 
+    /// Rounded down.
     pub fn convert_to_xtokens(&self, amount: Balance) -> Balance {
-        (U256::from(amount)
-            .checked_mul(U256::from(self.xtokens_amount))
-            .unwrap_or_else(|| env::panic_str("Mul will overflow"))
-            .checked_div(U256::from(self.amount))
-            .unwrap_or_else(|| env::panic_str("Div will overflow")))
-        .as_u128()
+        mul_div(amount, self.xtokens_amount, self.amount)
     }
 
+    /// Rounded down.
     pub fn convert_to_amount(&self, xtokens_amount: Balance) -> Balance {
-        (U256::from(xtokens_amount)
-            .checked_mul(U256::from(self.amount))
-            .unwrap_or_else(|| env::panic_str("Mul will overflow"))
-            .checked_div(U256::from(self.xtokens_amount))
-            .unwrap_or_else(|| env::panic_str("Div will overflow")))
-        .as_u128()
+        mul_div(xtokens_amount, self.amount, self.xtokens_amount)
+    }
+
+    /// Xtokens to mint for the deposit of `amount`,
+    /// `None` if the deposit is worth less than one xtoken.
+    pub fn shares_for_deposit(&self, amount: Balance) -> Option<StakeShares> {
+        let xtokens_amount = self.convert_to_xtokens(amount);
+        if xtokens_amount == 0 {
+            return None;
+        }
+        Some(StakeShares {
+            xtokens_amount,
+            dust: amount
+                .checked_sub(mul_div_ceil(
+                    xtokens_amount,
+                    self.amount,
+                    self.xtokens_amount,
+                ))
+                .unwrap_or_else(|| env::panic_str("Sub will overflow")),
+        })
+    }
+
+    /// Tokens to pay for the burn of `xtokens_amount`,
+    /// `None` if the xtokens are worth less than one token.
+    /// Unlike a deposit, the redeem creates no dust: the burned xtokens
+    /// are worth less than one token more than the payout.
+    pub fn amount_for_redeem(&self, xtokens_amount: Balance) -> Option<Balance> {
+        Some(self.convert_to_amount(xtokens_amount)).filter(|amount| *amount > 0)
+    }
+}
+
+/// `value * numerator / denominator` without intermediate overflow, rounded down.
+pub fn mul_div(value: u128, numerator: u128, denominator: u128) -> u128 {
+    (U256::from(value)
+        .checked_mul(U256::from(numerator))
+        .unwrap_or_else(|| env::panic_str("Mul will overflow"))
+        .checked_div(U256::from(denominator))
+        .unwrap_or_else(|| env::panic_str("Div will overflow")))
+    .as_u128()
+}
+
+/// `value * numerator / denominator` without intermediate overflow, rounded up.
+pub fn mul_div_ceil(value: u128, numerator: u128, denominator: u128) -> u128 {
+    let result = mul_div(value, numerator, denominator);
+    if (U256::from(result) * U256::from(denominator)) < U256::from(value) * U256::from(numerator) {
+        result + 1
+    } else {
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Contract;
+    use near_sdk::{
+        test_utils::VMContextBuilder, testing_env, AccountId, Gas, MockedBlockchain,
+        RuntimeFeesConfig, VMConfig,
+    };
+    use proptest::prelude::*;
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Stake {
+            account: usize,
+            amount: Balance,
+        },
+        Unstake {
+            account: usize,
+            percent: u128,
+        },
+        UnstakeRollback {
+            account: usize,
+            percent: u128,
+            refund_percent: u128,
+        },
+        AddToPool {
+            amount: Balance,
+        },
+    }
+
+    const ACCOUNTS: usize = 4;
+    const MAX_AMOUNT: Balance = 10_u128.pow(24);
+
+    fn account(index: usize) -> AccountId {
+        AccountId::new_unchecked(format!("account{}.near", index))
+    }
+
+    /// Every operation runs as a separate call with free gas,
+    /// the storage is kept.
+    fn context() {
+        testing_env!(
+            VMContextBuilder::new()
+                .prepaid_gas(Gas(300_000_000_000_000))
+                .build(),
+            VMConfig::free(),
+            RuntimeFeesConfig::free()
+        );
+    }
+
+    fn setup() -> Contract {
+        // Every case starts with an empty storage
+        env::set_blockchain_interface(MockedBlockchain::default());
+        context();
+        let mut contract = Contract::new(
+            None,
+            AccountId::new_unchecked("token.near".to_owned()),
+            AccountId::new_unchecked("lockup.near".to_owned()),
+        );
+        for index in 0..ACCOUNTS {
+            contract.internal_register_account(&account(index));
+        }
+        contract
+    }
+
+    /// Change the pool in the same way as the calls of the contract,
+    /// skipping the operations that the contract rejects.
+    fn apply(contract: &mut Contract, op: Op) {
+        context();
+        match op {
+            Op::Stake {
+                account: index,
+                amount,
+            } => {
+                if contract.xtoken_cost.shares_for_deposit(amount).is_some() {
+                    contract.stake_internal(&account(index), amount);
+                }
+            }
+            Op::Unstake {
+                account: index,
+                percent,
+            } => {
+                unstake(contract, &account(index), percent);
+            }
+            Op::UnstakeRollback {
+                account: index,
+                percent,
+                refund_percent,
+            } => {
+                let account_id = account(index);
+                if let Some((xtoken_amount, amount)) = unstake(contract, &account_id, percent) {
+                    let refund = amount * refund_percent / 100;
+                    if refund > 0 {
+                        contract.unstake_rollback_internal(
+                            &account_id,
+                            amount,
+                            xtoken_amount,
+                            refund,
+                        );
+                    }
+                }
+            }
+            Op::AddToPool { amount } => contract.add_to_pool_internal(amount),
+        }
+    }
+
+    fn unstake(
+        contract: &mut Contract,
+        account_id: &AccountId,
+        percent: u128,
+    ) -> Option<(Balance, Balance)> {
+        let xtoken_amount = contract.accounts.get(account_id).unwrap() * percent / 100;
+        let amount = contract.xtoken_cost.amount_for_redeem(xtoken_amount)?;
+        contract.unstake_internal(account_id, xtoken_amount, None);
+        Some((xtoken_amount, amount))
+    }
+
+    fn balances(contract: &Contract) -> Vec<Balance> {
+        (0..ACCOUNTS)
+            .map(|index| contract.accounts.get(&account(index)).unwrap())
+            .collect()
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            (0..ACCOUNTS, 1..MAX_AMOUNT)
+                .prop_map(|(account, amount)| Op::Stake { account, amount }),
+            (0..ACCOUNTS, 1..=100u128)
+                .prop_map(|(account, percent)| Op::Unstake { account, percent }),
+            (0..ACCOUNTS, 1..=100u128, 0..100u128).prop_map(
+                |(account, percent, refund_percent)| {
+                    Op::UnstakeRollback {
+                        account,
+                        percent,
+                        refund_percent,
+                    }
+                }
+            ),
+            (1..MAX_AMOUNT).prop_map(|amount| Op::AddToPool { amount }),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn total_supply_covers_xtokens(ops in proptest::collection::vec(op(), 1..50)) {
+            let mut contract = setup();
+            for op in ops {
+                apply(&mut contract, op);
+                let balances = balances(&contract);
+                prop_assert_eq!(
+                    contract.total_xtoken_supply,
+                    balances.iter().sum::<Balance>()
+                );
+                prop_assert!(
+                    contract.xtoken_cost.convert_to_amount(contract.total_xtoken_supply)
+                        <= contract.total_supply
+                );
+                let redeemable: Balance = balances
+                    .iter()
+                    .map(|xtokens_amount| contract.xtoken_cost.convert_to_amount(*xtokens_amount))
+                    .sum();
+                prop_assert!(redeemable <= contract.staked_supply());
+            }
+        }
+
+        #[test]
+        fn stake_and_unstake_never_gain(
+            amount in 1..MAX_AMOUNT,
+            cost_amount in 1..MAX_AMOUNT,
+            cost_xtokens_amount in 1..MAX_AMOUNT,
+        ) {
+            let xtoken_cost = XTokenCost::new(cost_amount, cost_xtokens_amount);
+            let shares = xtoken_cost.shares_for_deposit(amount);
+            prop_assume!(shares.is_some());
+            let shares = shares.unwrap();
+            let redeemed = xtoken_cost.convert_to_amount(shares.xtokens_amount);
+            prop_assert!(redeemed <= amount);
+            prop_assert!(redeemed + shares.dust <= amount);
+            prop_assert!(redeemed + shares.dust + 1 >= amount);
+        }
+    }
+
+    #[test]
+    fn reject_zero_output() {
+        assert_eq!(XTokenCost::new(3, 1).shares_for_deposit(2), None);
+        assert_eq!(
+            XTokenCost::default().amount_for_redeem(STARTED_COST - 1),
+            None
+        );
+        assert_eq!(
+            XTokenCost::default().amount_for_redeem(STARTED_COST),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn mul_div_rounding() {
+        assert_eq!(mul_div(7, 1, 2), 3);
+        assert_eq!(mul_div_ceil(7, 1, 2), 4);
+        assert_eq!(mul_div_ceil(8, 1, 2), 4);
+        assert_eq!(mul_div_ceil(u128::MAX, 3, 3), u128::MAX);
     }
 }
@@ -0,0 +1,356 @@
+use near_sdk::serde_json;
+use test_utils::{token, utils::*, SandboxEnvironment};
+
+#[tokio::test]
+async fn emergency_withdraw() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+
+    // Make storage deposit for owner
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let balance = token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await?;
+
+    // Stake and lock for 52 weeks
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": amount.to_string(),
+            "msg": serde_json::json!({ "Lock": { "weeks": 52 } }).to_string(),
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let outcome = sandbox
+        .owner
+        .call(sandbox.staking.id(), "set_emergency_mode")
+        .args_json(serde_json::json!({ "enabled": true }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"emergency_mode\"")));
+
+    // Staking is disabled
+    let result = sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": amount.to_string(),
+            "msg": "\"Stake\"",
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result();
+
+    assert!(result.is_ok());
+    let outcome = result.unwrap();
+    let failure = outcome.receipt_failures()[0];
+    assert!(failure.is_failure());
+    assert!(format!("{:?}", failure).contains("Disabled in the emergency mode"));
+
+    // Locked tokens are withdrawn directly
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "emergency_withdraw")
+        .args_json(serde_json::json!({}))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    assert_eq!(
+        0,
+        token::ft_balance_of(&sandbox.staking, sandbox.owner.id()).await?
+    );
+    assert_eq!(
+        balance,
+        token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn emergency_withdraw_when_off() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+
+    let result = sandbox
+        .owner
+        .call(sandbox.staking.id(), "emergency_withdraw")
+        .args_json(serde_json::json!({}))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result();
+
+    assert!(result.is_err());
+    let outcome = result.unwrap_err();
+    let failure = outcome.receipt_failures()[0];
+    assert!(failure.is_failure());
+    assert!(format!("{:?}", failure).contains("The emergency mode is off"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn emergency_withdraw_transfer_failure() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+    let user = sandbox
+        .owner
+        .create_subaccount("user")
+        .initial_balance(10 * NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "storage_deposit")
+        .args_json(serde_json::json!({ "account_id": user.id() }))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer")
+        .args_json(serde_json::json!({
+            "receiver_id": user.id(),
+            "amount": amount.to_string(),
+        }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+    user.call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Stake and lock for 52 weeks
+    user.call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": amount.to_string(),
+            "msg": serde_json::json!({ "Lock": { "weeks": 52 } }).to_string(),
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+    let xtoken_balance = token::ft_balance_of(&sandbox.staking, user.id()).await?;
+    let lock: serde_json::Value = sandbox
+        .staking
+        .view(
+            "get_stake_lock",
+            serde_json::json!({ "account_id": user.id() })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(lock["xtoken_amount"], xtoken_balance.to_string());
+
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "set_emergency_mode")
+        .args_json(serde_json::json!({ "enabled": true }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // The transfer fails as the user is not registered in the token anymore
+    user.call(sandbox.token.id(), "storage_unregister")
+        .args_json(serde_json::json!({}))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+    let outcome = user
+        .call(sandbox.staking.id(), "emergency_withdraw")
+        .args_json(serde_json::json!({}))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"unstake_rollback\"")));
+
+    // Both the xtokens and the lock are restored
+    assert_eq!(
+        xtoken_balance,
+        token::ft_balance_of(&sandbox.staking, user.id()).await?
+    );
+    let restored_lock: serde_json::Value = sandbox
+        .staking
+        .view(
+            "get_stake_lock",
+            serde_json::json!({ "account_id": user.id() })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(lock["xtoken_amount"], restored_lock["xtoken_amount"]);
+    assert_eq!(lock["unlock_on"], restored_lock["unlock_on"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn emergency_withdraw_transfer_failure_keeps_nft() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+    let token_id = "hero:1";
+    let user = sandbox
+        .owner
+        .create_subaccount("user")
+        .initial_balance(10 * NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "add_nft_contract")
+        .args_json(serde_json::json!({ "nft_contract_id": sandbox.nft.id() }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "storage_deposit")
+        .args_json(serde_json::json!({ "account_id": user.id() }))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer")
+        .args_json(serde_json::json!({
+            "receiver_id": user.id(),
+            "amount": amount.to_string(),
+        }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+    user.call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+    user.call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": amount.to_string(),
+            "msg": "\"Stake\""
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    sandbox
+        .owner
+        .call(sandbox.nft.id(), "nft_mint")
+        .args_json(serde_json::json!({
+            "token_id": token_id,
+            "owner_id": user.id(),
+        }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+    user.call(sandbox.nft.id(), "nft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.staking.id(),
+            "token_id": token_id,
+            "msg": "",
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "set_emergency_mode")
+        .args_json(serde_json::json!({ "enabled": true }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // The transfer fails as the user is not registered in the token anymore
+    user.call(sandbox.token.id(), "storage_unregister")
+        .args_json(serde_json::json!({}))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+    user.call(sandbox.staking.id(), "emergency_withdraw")
+        .args_json(serde_json::json!({}))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // The NFT stays staked with the restored xtokens
+    let token: serde_json::Value = sandbox
+        .nft
+        .view(
+            "nft_token",
+            serde_json::json!({ "token_id": token_id })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(token["owner_id"], sandbox.staking.id().to_string());
+    assert_eq!(
+        amount * 1_000,
+        token::ft_balance_of(&sandbox.staking, user.id()).await?
+    );
+
+    Ok(())
+}
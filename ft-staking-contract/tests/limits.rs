@@ -0,0 +1,275 @@
+use near_sdk::serde_json;
+use test_utils::{token, utils::*, SandboxEnvironment};
+
+#[tokio::test]
+async fn stake_over_account_limit() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "set_stake_limits")
+        .args_json(serde_json::json!({
+            "limits": {
+                "min_stake": LIS.to_string(),
+                "max_stake_per_account": (amount / 2).to_string(),
+            }
+        }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Make storage deposit for owner
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let balance = token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await?;
+
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": amount.to_string(),
+            "msg": "\"Stake\""
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Only a half is staked, the rest is refunded
+    assert_eq!(
+        amount / 2 * 1_000,
+        token::ft_balance_of(&sandbox.staking, sandbox.owner.id()).await?
+    );
+    assert_eq!(
+        balance - amount / 2,
+        token::ft_balance_of(&sandbox.token, sandbox.owner.id()).await?
+    );
+
+    let result = sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": amount.to_string(),
+            "msg": "\"Stake\""
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result();
+
+    assert!(result.is_ok());
+    let outcome = result.unwrap();
+    let failure = outcome.receipt_failures()[0];
+    assert!(failure.is_failure());
+    assert!(format!("{:?}", failure).contains("Stake limit exceeded"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn stake_less_than_min() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "set_stake_limits")
+        .args_json(serde_json::json!({
+            "limits": { "min_stake": LIS.to_string() }
+        }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Make storage deposit for owner
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let result = sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": (LIS / 2).to_string(),
+            "msg": "\"Stake\""
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result();
+
+    assert!(result.is_ok());
+    let outcome = result.unwrap();
+    let failure = outcome.receipt_failures()[0];
+    assert!(failure.is_failure());
+    assert!(format!("{:?}", failure).contains("The amount should be at least"));
+    assert_eq!(
+        0,
+        token::ft_balance_of(&sandbox.staking, sandbox.owner.id()).await?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn stake_less_than_min_after_limit() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+
+    // Min stake over the max stake is rejected
+    let result = sandbox
+        .owner
+        .call(sandbox.staking.id(), "set_stake_limits")
+        .args_json(serde_json::json!({
+            "limits": {
+                "min_stake": (10 * LIS).to_string(),
+                "max_stake_per_account": LIS.to_string(),
+            }
+        }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result();
+    assert!(format!("{:?}", result.unwrap_err())
+        .contains("The min stake should not exceed the max stakes"));
+
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "set_stake_limits")
+        .args_json(serde_json::json!({
+            "limits": {
+                "min_stake": (10 * LIS).to_string(),
+                "max_stake_per_account": (15 * LIS).to_string(),
+            }
+        }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Make storage deposit for owner
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    for _ in 0..2 {
+        sandbox
+            .owner
+            .call(sandbox.token.id(), "ft_transfer_call")
+            .args_json(serde_json::json!({
+                "receiver_id": STAKING_CONTRACT_ACCOUNT,
+                "amount": (10 * LIS).to_string(),
+                "msg": "\"Stake\""
+            }))
+            .deposit(YOCTO)
+            .gas(300000000000000)
+            .transact()
+            .await?
+            .into_result()?;
+    }
+
+    // Only 5 LIS of the second stake fit the account limit, less than the min stake
+    assert_eq!(
+        10 * LIS * 1_000,
+        token::ft_balance_of(&sandbox.staking, sandbox.owner.id()).await?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unstake_less_than_min() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 10 * LIS;
+
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "set_stake_limits")
+        .args_json(serde_json::json!({
+            "limits": { "min_stake": "0", "min_unstake": LIS.to_string() }
+        }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Make storage deposit for owner
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": amount.to_string(),
+            "msg": "\"Stake\""
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let result = sandbox
+        .owner
+        .call(sandbox.staking.id(), "unstake")
+        .args_json(serde_json::json!({ "xtoken_amount": "1000" }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result();
+    assert!(format!("{:?}", result.unwrap_err()).contains("The unstaked amount should be at least"));
+
+    // The whole balance can be unstaked
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "unstake")
+        .args_json(serde_json::json!({}))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+    assert_eq!(
+        0,
+        token::ft_balance_of(&sandbox.staking, sandbox.owner.id()).await?
+    );
+
+    Ok(())
+}
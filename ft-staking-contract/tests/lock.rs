@@ -0,0 +1,211 @@
+use near_sdk::{json_types::U128, serde_json};
+use test_utils::{token, utils::*, SandboxEnvironment};
+
+#[tokio::test]
+async fn stake_and_lock() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+
+    // Make storage deposit for owner
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Make `ft_transfer_call` to stake and lock for 52 weeks
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": amount.to_string(),
+            "msg": serde_json::json!({ "Lock": { "weeks": 52 } }).to_string(),
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let balance = token::ft_balance_of(&sandbox.staking, sandbox.owner.id()).await?;
+    assert_eq!(balance, amount * 1_000);
+
+    let lock: serde_json::Value = sandbox
+        .staking
+        .view(
+            "get_stake_lock",
+            serde_json::json!({ "account_id": sandbox.owner.id() })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(lock["xtoken_amount"], (amount * 1_000).to_string());
+
+    // Reward weight of the fresh max lock is boosted
+    let weight: U128 = sandbox
+        .staking
+        .view(
+            "get_reward_weight",
+            serde_json::json!({ "account_id": sandbox.owner.id() })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert!(weight.0 > balance);
+    assert!(weight.0 <= balance * 5 / 2);
+
+    // Locked xtokens can't be unstaked
+    let result = sandbox
+        .owner
+        .call(sandbox.staking.id(), "unstake")
+        .args_json(serde_json::json!({ "xtoken_amount": "1000" }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result();
+
+    assert!(result.is_err());
+    let outcome = result.unwrap_err();
+    let failure = outcome.receipt_failures()[0];
+    assert!(failure.is_failure());
+    assert!(format!("{:?}", failure).contains("Not enough unlocked xtokens"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn lock_invalid_period() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+
+    // Make storage deposit for owner
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let result = sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": amount.to_string(),
+            "msg": serde_json::json!({ "Lock": { "weeks": 53 } }).to_string(),
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result();
+
+    assert!(result.is_ok());
+    let outcome = result.unwrap();
+    let failure = outcome.receipt_failures()[0];
+    assert!(failure.is_failure());
+    assert!(format!("{:?}", failure).contains("Lock period should be from 1 to 52 weeks"));
+    assert_eq!(
+        0,
+        token::ft_balance_of(&sandbox.staking, sandbox.owner.id()).await?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unlock_early_with_penalty() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+
+    // Make storage deposit for owner
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Stake and lock for 52 weeks
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": amount.to_string(),
+            "msg": serde_json::json!({ "Lock": { "weeks": 52 } }).to_string(),
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Extend the lock with more tokens
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": amount.to_string(),
+            "msg": serde_json::json!({ "ExtendLock": { "weeks": 52 } }).to_string(),
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let balance = token::ft_balance_of(&sandbox.staking, sandbox.owner.id()).await?;
+    assert_eq!(balance, 2 * amount * 1_000);
+
+    let penalty: U128 = sandbox
+        .owner
+        .call(sandbox.staking.id(), "unlock_early")
+        .args_json(serde_json::json!({}))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .json()?;
+
+    // Almost a whole year left, so almost a half is burned
+    assert!(penalty.0 > 0);
+    assert!(penalty.0 <= balance / 2);
+    assert_eq!(
+        balance - penalty.0,
+        token::ft_balance_of(&sandbox.staking, sandbox.owner.id()).await?
+    );
+
+    // Tokens are unlocked now
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "unstake")
+        .args_json(serde_json::json!({}))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    assert_eq!(
+        0,
+        token::ft_balance_of(&sandbox.staking, sandbox.owner.id()).await?
+    );
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use near_sdk::{json_types::U128, serde_json};
+use test_utils::{utils::*, SandboxEnvironment};
+
+#[tokio::test]
+async fn nft_boost_and_return() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+    let token_id = "hero:1";
+
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "add_nft_contract")
+        .args_json(serde_json::json!({ "nft_contract_id": sandbox.nft.id() }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // NFTs of the `hero` series double the reward weight
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "set_nft_multiplier")
+        .args_json(serde_json::json!({
+            "nft_contract_id": sandbox.nft.id(),
+            "series": "hero",
+            "multiplier": 20_000,
+        }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Make storage deposit for owner
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": amount.to_string(),
+            "msg": "\"Stake\""
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    sandbox
+        .owner
+        .call(sandbox.nft.id(), "nft_mint")
+        .args_json(serde_json::json!({
+            "token_id": token_id,
+            "owner_id": sandbox.owner.id(),
+        }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+
+    sandbox
+        .owner
+        .call(sandbox.nft.id(), "nft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.staking.id(),
+            "token_id": token_id,
+            "msg": "",
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let weight: U128 = sandbox
+        .staking
+        .view(
+            "get_reward_weight",
+            serde_json::json!({ "account_id": sandbox.owner.id() })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(weight.0, 2 * amount * 1_000);
+
+    // NFT is returned with the last xtokens
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "unstake")
+        .args_json(serde_json::json!({}))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let token: serde_json::Value = sandbox
+        .nft
+        .view(
+            "nft_token",
+            serde_json::json!({ "token_id": token_id })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(token["owner_id"], sandbox.owner.id().to_string());
+
+    Ok(())
+}
@@ -0,0 +1,258 @@
+use near_sdk::serde_json;
+use test_utils::{lockup, token, utils::*, SandboxEnvironment};
+
+#[tokio::test]
+async fn unstake_for_by_operator() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+
+    // Make storage deposit for owner
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Make `ft_transfer_call` to stake
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": amount.to_string(),
+            "msg": "\"Stake\""
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let outcome = sandbox
+        .owner
+        .call(sandbox.staking.id(), "grant_operator")
+        .args_json(serde_json::json!({
+            "operator_id": sandbox.backend.id(),
+            "allowance": "2000",
+        }))
+        .deposit(NEAR / 100)
+        .transact()
+        .await?
+        .into_result()?;
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"operator_grant\"")));
+
+    let num_lockups = lockup::get_num_lockups(&sandbox.lockup).await?;
+
+    sandbox
+        .backend
+        .call(sandbox.staking.id(), "unstake_for")
+        .args_json(serde_json::json!({
+            "account_id": sandbox.owner.id(),
+            "xtoken_amount": "1000",
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    assert_eq!(
+        (amount - 1) * 1_000,
+        token::ft_balance_of(&sandbox.staking, sandbox.owner.id()).await?
+    );
+    // Unstaked tokens go to the lockup of the owner
+    assert_eq!(
+        num_lockups + 1,
+        lockup::get_num_lockups(&sandbox.lockup).await?
+    );
+
+    // Allowance has only 1000 xtokens left
+    let result = sandbox
+        .backend
+        .call(sandbox.staking.id(), "unstake_for")
+        .args_json(serde_json::json!({
+            "account_id": sandbox.owner.id(),
+            "xtoken_amount": "2000",
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result();
+
+    assert!(result.is_err());
+    let outcome = result.unwrap_err();
+    let failure = outcome.receipt_failures()[0];
+    assert!(failure.is_failure());
+    assert!(format!("{:?}", failure).contains("Operator allowance exceeded"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unstake_for_after_revoke() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+
+    // Make storage deposit for owner
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Make `ft_transfer_call` to stake
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": amount.to_string(),
+            "msg": "\"Stake\""
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "grant_operator")
+        .args_json(serde_json::json!({ "operator_id": sandbox.backend.id() }))
+        .deposit(NEAR / 100)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let outcome = sandbox
+        .owner
+        .call(sandbox.staking.id(), "revoke_operator")
+        .args_json(serde_json::json!({ "operator_id": sandbox.backend.id() }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"operator_revoke\"")));
+
+    let result = sandbox
+        .backend
+        .call(sandbox.staking.id(), "unstake_for")
+        .args_json(serde_json::json!({ "account_id": sandbox.owner.id() }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result();
+
+    assert!(result.is_err());
+    let outcome = result.unwrap_err();
+    let failure = outcome.receipt_failures()[0];
+    assert!(failure.is_failure());
+    assert!(format!("{:?}", failure).contains("Not an operator of the account"));
+    assert_eq!(
+        amount * 1_000,
+        token::ft_balance_of(&sandbox.staking, sandbox.owner.id()).await?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn restake_for_by_operator() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+
+    // Make storage deposit for owner
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Transfer some LIS to the operator
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer")
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.backend.id(),
+            "amount": (10 * LIS).to_string(),
+        }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let restake = || async {
+        sandbox
+            .backend
+            .call(sandbox.token.id(), "ft_transfer_call")
+            .args_json(serde_json::json!({
+                "receiver_id": STAKING_CONTRACT_ACCOUNT,
+                "amount": LIS.to_string(),
+                "msg": serde_json::json!({
+                    "RestakeFor": { "account_id": sandbox.owner.id() }
+                })
+                .to_string(),
+            }))
+            .deposit(YOCTO)
+            .gas(300000000000000)
+            .transact()
+            .await?
+            .into_result()
+            .map_err(anyhow::Error::from)
+    };
+
+    // Restake needs a grant
+    let outcome = restake().await?;
+    let failure = outcome.receipt_failures()[0];
+    assert!(failure.is_failure());
+    assert!(format!("{:?}", failure).contains("Not an operator of the account"));
+
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "grant_operator")
+        .args_json(serde_json::json!({
+            "operator_id": sandbox.backend.id(),
+            "allowance": (LIS * 1_000).to_string(),
+        }))
+        .deposit(NEAR / 100)
+        .transact()
+        .await?
+        .into_result()?;
+
+    restake().await?;
+    assert_eq!(
+        LIS * 1_000,
+        token::ft_balance_of(&sandbox.staking, sandbox.owner.id()).await?
+    );
+
+    // The restaked xtokens used up the allowance
+    let outcome = restake().await?;
+    let failure = outcome.receipt_failures()[0];
+    assert!(failure.is_failure());
+    assert!(format!("{:?}", failure).contains("Operator allowance exceeded"));
+    assert_eq!(
+        9 * LIS,
+        token::ft_balance_of(&sandbox.token, sandbox.backend.id()).await?
+    );
+
+    Ok(())
+}
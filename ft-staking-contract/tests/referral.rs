@@ -0,0 +1,269 @@
+use near_sdk::{json_types::U128, serde_json};
+use std::collections::HashMap;
+use test_utils::{token, utils::*, SandboxEnvironment};
+use workspaces::{network::Sandbox, Account, Contract, Worker};
+
+/// Environment with a reward token, 10% referral fee
+/// and a user with 100 LIS registered in staking.
+async fn setup(
+    worker: &Worker<Sandbox>,
+) -> anyhow::Result<(SandboxEnvironment, Account, Contract)> {
+    let sandbox = SandboxEnvironment::new(worker).await?;
+    let user = sandbox
+        .owner
+        .create_subaccount("user")
+        .initial_balance(10 * NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+    let reward_token = token::new(
+        worker,
+        Some(sandbox.owner.id().clone()),
+        None,
+        STAKING_CONTRACT_ACCOUNT.parse()?,
+        LOCKUP_CONTRACT_ACCOUNT.parse()?,
+    )
+    .await?;
+
+    sandbox
+        .owner
+        .call(reward_token.id(), "storage_deposit")
+        .deposit(NEAR)
+        .args_json(serde_json::json!({ "account_id": sandbox.staking.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "add_reward_token")
+        .args_json(serde_json::json!({ "token_account_id": reward_token.id() }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // 10% of the rewards go to the referrer
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "set_referral_fee")
+        .args_json(serde_json::json!({ "referral_fee": 1_000 }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Storage deposits for the referrer and the referee
+    for account in [&sandbox.owner, &user] {
+        account
+            .call(sandbox.staking.id(), "storage_deposit")
+            .args_json(serde_json::json!({}))
+            .deposit(NEAR)
+            .transact()
+            .await?
+            .into_result()?;
+    }
+    user.call(sandbox.token.id(), "storage_deposit")
+        .deposit(NEAR)
+        .args_json(serde_json::json!({}))
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Transfer some LIS to user
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer")
+        .args_json(serde_json::json!({
+            "receiver_id": user.id(),
+            "amount": (100 * LIS).to_string(),
+        }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+
+    Ok((sandbox, user, reward_token))
+}
+
+async fn stake(
+    sandbox: &SandboxEnvironment,
+    user: &Account,
+    amount: u128,
+    msg: serde_json::Value,
+) -> anyhow::Result<()> {
+    user.call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.staking.id(),
+            "amount": amount.to_string(),
+            "msg": msg.to_string(),
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(())
+}
+
+async fn add_reward(
+    sandbox: &SandboxEnvironment,
+    reward_token: &Contract,
+    amount: u128,
+) -> anyhow::Result<()> {
+    sandbox
+        .owner
+        .call(reward_token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.staking.id(),
+            "amount": amount.to_string(),
+            "msg": "\"AddToPool\"",
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(())
+}
+
+async fn checkpoint(sandbox: &SandboxEnvironment, account: &Account) -> anyhow::Result<()> {
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "checkpoint")
+        .args_json(serde_json::json!({ "account_id": account.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(())
+}
+
+async fn get_unclaimed_rewards(
+    sandbox: &SandboxEnvironment,
+    account: &Account,
+) -> anyhow::Result<HashMap<String, U128>> {
+    Ok(sandbox
+        .staking
+        .view(
+            "get_unclaimed_rewards",
+            serde_json::json!({ "account_id": account.id() })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?)
+}
+
+async fn get_referral_stats(sandbox: &SandboxEnvironment) -> anyhow::Result<serde_json::Value> {
+    Ok(sandbox
+        .staking
+        .view(
+            "get_referral_stats",
+            serde_json::json!({ "account_id": sandbox.owner.id() })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?)
+}
+
+#[tokio::test]
+async fn referrer_earns_cut() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let (sandbox, user, reward_token) = setup(&worker).await?;
+    let amount = 100 * LIS;
+    let reward_amount = 10 * LIS;
+
+    stake(
+        &sandbox,
+        &user,
+        amount,
+        serde_json::json!({ "StakeWithReferral": { "referrer": sandbox.owner.id() } }),
+    )
+    .await?;
+    add_reward(&sandbox, &reward_token, reward_amount).await?;
+
+    let rewards = get_unclaimed_rewards(&sandbox, &user).await?;
+    assert_eq!(
+        rewards[reward_token.id().as_str()].0,
+        reward_amount / 10 * 9
+    );
+
+    // Settle the referee rewards
+    checkpoint(&sandbox, &user).await?;
+
+    let stats = get_referral_stats(&sandbox).await?;
+    assert_eq!(stats["num_referees"], 1);
+    assert_eq!(
+        stats["rewards"][reward_token.id().as_str()]["unclaimed"],
+        (reward_amount / 10).to_string()
+    );
+    let balance = token::ft_balance_of(&reward_token, sandbox.owner.id()).await?;
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "claim_referral_reward")
+        .args_json(serde_json::json!({ "token_account_id": reward_token.id() }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    assert_eq!(
+        balance + reward_amount / 10,
+        token::ft_balance_of(&reward_token, sandbox.owner.id()).await?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn referrer_takes_no_cut_of_earlier_rewards() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let (sandbox, user, reward_token) = setup(&worker).await?;
+    let reward_amount = 10 * LIS;
+
+    // Rewards accrue before the referrer is set
+    stake(&sandbox, &user, 50 * LIS, serde_json::json!("Stake")).await?;
+    add_reward(&sandbox, &reward_token, reward_amount).await?;
+
+    stake(
+        &sandbox,
+        &user,
+        50 * LIS,
+        serde_json::json!({ "StakeWithReferral": { "referrer": sandbox.owner.id() } }),
+    )
+    .await?;
+    checkpoint(&sandbox, &user).await?;
+
+    let rewards = get_unclaimed_rewards(&sandbox, &user).await?;
+    assert_eq!(rewards[reward_token.id().as_str()].0, reward_amount);
+    let stats = get_referral_stats(&sandbox).await?;
+    assert_eq!(stats["num_referees"], 1);
+    assert!(stats["rewards"].get(reward_token.id().as_str()).is_none());
+
+    // The fee change applies to the rewards distributed afterwards
+    add_reward(&sandbox, &reward_token, reward_amount).await?;
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "set_referral_fee")
+        .args_json(serde_json::json!({ "referral_fee": 5_000 }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+    checkpoint(&sandbox, &user).await?;
+
+    let rewards = get_unclaimed_rewards(&sandbox, &user).await?;
+    assert_eq!(
+        rewards[reward_token.id().as_str()].0,
+        reward_amount + reward_amount / 10 * 9
+    );
+    let stats = get_referral_stats(&sandbox).await?;
+    assert_eq!(
+        stats["rewards"][reward_token.id().as_str()]["unclaimed"],
+        (reward_amount / 10).to_string()
+    );
+
+    Ok(())
+}
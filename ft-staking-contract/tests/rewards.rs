@@ -0,0 +1,180 @@
+use near_sdk::{json_types::U128, serde_json};
+use std::collections::HashMap;
+use test_utils::{token, utils::*, SandboxEnvironment};
+
+#[tokio::test]
+async fn claim_reward_token() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 100 * LIS;
+    let reward_amount = 10 * LIS;
+    let partner = sandbox
+        .owner
+        .create_subaccount("partner")
+        .initial_balance(50 * NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+    let reward_token = token::new(
+        &worker,
+        Some(partner.id().clone()),
+        None,
+        STAKING_CONTRACT_ACCOUNT.parse()?,
+        LOCKUP_CONTRACT_ACCOUNT.parse()?,
+    )
+    .await?;
+
+    // Staking contract and owner should be registered on the reward token
+    for account_id in [sandbox.staking.id(), sandbox.owner.id()] {
+        partner
+            .call(reward_token.id(), "storage_deposit")
+            .deposit(NEAR)
+            .args_json(serde_json::json!({ "account_id": account_id }))
+            .transact()
+            .await?
+            .into_result()?;
+    }
+
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "add_reward_token")
+        .args_json(serde_json::json!({ "token_account_id": reward_token.id() }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Make storage deposit for owner
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": amount.to_string(),
+            "msg": "\"Stake\"",
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Partner deposits rewards
+    partner
+        .call(reward_token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": reward_amount.to_string(),
+            "msg": "\"AddToPool\"",
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let rewards: HashMap<String, U128> = sandbox
+        .staking
+        .view(
+            "get_unclaimed_rewards",
+            serde_json::json!({ "account_id": sandbox.owner.id() })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?;
+    // The only staker gets all rewards
+    assert_eq!(rewards[reward_token.id().as_str()].0, reward_amount);
+
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "claim_reward")
+        .args_json(serde_json::json!({ "token_account_id": reward_token.id() }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    assert_eq!(
+        reward_amount,
+        token::ft_balance_of(&reward_token, sandbox.owner.id()).await?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn add_reward_without_stakers() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let reward_amount = 10 * LIS;
+    let partner = sandbox
+        .owner
+        .create_subaccount("partner")
+        .initial_balance(50 * NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+    let reward_token = token::new(
+        &worker,
+        Some(partner.id().clone()),
+        None,
+        STAKING_CONTRACT_ACCOUNT.parse()?,
+        LOCKUP_CONTRACT_ACCOUNT.parse()?,
+    )
+    .await?;
+
+    partner
+        .call(reward_token.id(), "storage_deposit")
+        .deposit(NEAR)
+        .args_json(serde_json::json!({ "account_id": sandbox.staking.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "add_reward_token")
+        .args_json(serde_json::json!({ "token_account_id": reward_token.id() }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let balance = token::ft_balance_of(&reward_token, partner.id()).await?;
+
+    partner
+        .call(reward_token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": STAKING_CONTRACT_ACCOUNT,
+            "amount": reward_amount.to_string(),
+            "msg": "\"AddToPool\"",
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Nobody to distribute to, so the deposit is refunded
+    assert_eq!(
+        balance,
+        token::ft_balance_of(&reward_token, partner.id()).await?
+    );
+    assert_eq!(
+        0,
+        token::ft_balance_of(&reward_token, sandbox.staking.id()).await?
+    );
+
+    Ok(())
+}
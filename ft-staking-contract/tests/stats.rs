@@ -0,0 +1,112 @@
+use near_sdk::{json_types::U128, serde_json};
+use test_utils::{utils::*, SandboxEnvironment};
+
+#[tokio::test]
+async fn pool_info_and_apr() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let amount = 1000 * LIS;
+
+    // Make storage deposit for owner
+    sandbox
+        .owner
+        .call(sandbox.staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Make `ft_transfer_call` to stake
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.staking.id(),
+            "amount": amount.to_string(),
+            "msg": "\"Stake\"",
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Started price is 1 token per 1000 xtokens
+    let price: U128 = sandbox
+        .staking
+        .view(
+            "get_xtoken_price",
+            serde_json::json!({}).to_string().into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(price.0, LIS / 1_000);
+
+    // Owner add tokens to pool
+    for _ in 0..2 {
+        sandbox
+            .owner
+            .call(sandbox.token.id(), "ft_transfer_call")
+            .args_json(serde_json::json!({
+                "receiver_id": sandbox.staking.id(),
+                "amount": amount.to_string(),
+                "msg": "\"AddToPool\"",
+            }))
+            .deposit(YOCTO)
+            .gas(300000000000000)
+            .transact()
+            .await?
+            .into_result()?;
+        worker.fast_forward(100).await?;
+    }
+
+    let pool_info: serde_json::Value = sandbox
+        .staking
+        .view(
+            "get_pool_info",
+            serde_json::json!({}).to_string().into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(pool_info["total_supply"], (3 * amount).to_string());
+    assert_eq!(
+        pool_info["total_xtoken_supply"],
+        (amount * 1_000).to_string()
+    );
+    assert_eq!(pool_info["xtoken_price"], (3 * LIS / 1_000).to_string());
+
+    let staked: U128 = sandbox
+        .staking
+        .view(
+            "get_account_staked_lis",
+            serde_json::json!({ "account_id": sandbox.owner.id() })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(staked.0, 3 * amount);
+
+    // Both deposits are within a week, so only the first one is recorded
+    let snapshots: Vec<serde_json::Value> = sandbox
+        .staking
+        .view(
+            "get_price_snapshots",
+            serde_json::json!({}).to_string().into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0]["xtoken_price"], (2 * LIS / 1_000).to_string());
+
+    // Price has grown since the snapshot
+    let apr: u32 = sandbox
+        .staking
+        .view("get_apr", serde_json::json!({}).to_string().into_bytes())
+        .await?
+        .json()?;
+    assert!(apr > 0);
+
+    Ok(())
+}
@@ -0,0 +1,217 @@
+use near_sdk::{json_types::U128, serde_json};
+use test_utils::{fake_lockup, staking, token, utils::*, SandboxEnvironment};
+use workspaces::{network::Sandbox, Contract, Worker};
+
+const AMOUNT: u128 = 100 * LIS;
+
+/// Staking with a fake lockup that keeps only a half of the tokens,
+/// the owner stakes `AMOUNT`.
+async fn setup(
+    worker: &Worker<Sandbox>,
+) -> anyhow::Result<(SandboxEnvironment, Contract, Contract)> {
+    let sandbox = SandboxEnvironment::new(worker).await?;
+    let fake_lockup = fake_lockup::new(worker).await?;
+    let staking = staking::new(
+        worker,
+        Some(sandbox.owner.id().clone()),
+        sandbox.token.id().clone(),
+        fake_lockup.id().clone(),
+    )
+    .await?;
+
+    for account_id in [staking.id(), fake_lockup.id()] {
+        sandbox
+            .owner
+            .call(sandbox.token.id(), "storage_deposit")
+            .deposit(NEAR)
+            .args_json(serde_json::json!({ "account_id": account_id }))
+            .transact()
+            .await?
+            .into_result()?;
+    }
+
+    // Make storage deposit for owner
+    sandbox
+        .owner
+        .call(staking.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))
+        .deposit(NEAR)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Make `ft_transfer_call` to stake
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": staking.id(),
+            "amount": AMOUNT.to_string(),
+            "msg": "\"Stake\""
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    Ok((sandbox, fake_lockup, staking))
+}
+
+async fn unstake(
+    sandbox: &SandboxEnvironment,
+    staking: &Contract,
+    xtoken_amount: u128,
+) -> anyhow::Result<()> {
+    let outcome = sandbox
+        .owner
+        .call(staking.id(), "unstake")
+        .args_json(serde_json::json!({ "xtoken_amount": xtoken_amount.to_string() }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"unstake_rollback\"")));
+    Ok(())
+}
+
+#[tokio::test]
+async fn unstake_partial_refund_from_lockup() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let (sandbox, fake_lockup, staking) = setup(&worker).await?;
+
+    // Unstake xtokens worth 1 LIS, the fake lockup keeps a half of it
+    unstake(&sandbox, &staking, LIS * 1_000).await?;
+
+    // Exactly a half of the burned xtokens is restored
+    let balance = token::ft_balance_of(&staking, sandbox.owner.id()).await?;
+    assert_eq!(balance, (AMOUNT - LIS) * 1_000 + 500 * LIS);
+    assert_eq!(balance, staking::ft_total_supply(&staking).await?);
+    assert_eq!(
+        LIS / 2,
+        token::ft_balance_of(&sandbox.token, fake_lockup.id()).await?
+    );
+    assert_eq!(
+        AMOUNT - LIS / 2,
+        token::ft_balance_of(&sandbox.token, staking.id()).await?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unstake_partial_refund_after_price_change() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let (sandbox, fake_lockup, staking) = setup(&worker).await?;
+
+    // The fake lockup adds its own tokens to the pool before the refund
+    let added = 10 * LIS;
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer")
+        .args_json(serde_json::json!({
+            "receiver_id": fake_lockup.id(),
+            "amount": added.to_string(),
+        }))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+    sandbox
+        .owner
+        .call(fake_lockup.id(), "set_add_to_pool")
+        .args_json(serde_json::json!({ "amount": added.to_string() }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Xtokens worth 3 LIS and a fraction, which is rounded down on unstake
+    let xtoken_amount = 3 * LIS * 1_000 + 1;
+    unstake(&sandbox, &staking, xtoken_amount).await?;
+
+    // A half of the burned xtokens is restored rounded up in favour of the owner
+    let balance = token::ft_balance_of(&staking, sandbox.owner.id()).await?;
+    assert_eq!(balance, AMOUNT * 1_000 - xtoken_amount + 1_500 * LIS + 1);
+    assert_eq!(balance, staking::ft_total_supply(&staking).await?);
+    assert_eq!(
+        3 * LIS / 2,
+        token::ft_balance_of(&sandbox.token, fake_lockup.id()).await?
+    );
+    assert_eq!(
+        AMOUNT - 3 * LIS / 2 + added,
+        token::ft_balance_of(&sandbox.token, staking.id()).await?
+    );
+    // The restored xtokens share the added tokens, the pool still covers all of them
+    let staked: U128 = staking
+        .view(
+            "get_account_staked_lis",
+            serde_json::json!({ "account_id": sandbox.owner.id() })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(AMOUNT - 3 * LIS / 2 + added, staked.0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unstake_for_partial_refund_restores_allowance() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let (sandbox, _, staking) = setup(&worker).await?;
+
+    sandbox
+        .owner
+        .call(staking.id(), "grant_operator")
+        .args_json(serde_json::json!({
+            "operator_id": sandbox.backend.id(),
+            "allowance": (2 * LIS * 1_000).to_string(),
+        }))
+        .deposit(NEAR / 100)
+        .transact()
+        .await?
+        .into_result()?;
+
+    // The fake lockup keeps a half of the unstaked tokens
+    let outcome = sandbox
+        .backend
+        .call(staking.id(), "unstake_for")
+        .args_json(serde_json::json!({
+            "account_id": sandbox.owner.id(),
+            "xtoken_amount": (LIS * 1_000).to_string(),
+        }))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"unstake_rollback\"")));
+
+    // The restored xtokens are returned to the allowance
+    let grant: serde_json::Value = staking
+        .view(
+            "get_operator_grant",
+            serde_json::json!({
+                "account_id": sandbox.owner.id(),
+                "operator_id": sandbox.backend.id(),
+            })
+            .to_string()
+            .into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(
+        grant["allowance"],
+        serde_json::json!((1_500 * LIS).to_string())
+    );
+
+    Ok(())
+}
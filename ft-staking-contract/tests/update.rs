@@ -1,4 +1,4 @@
-use test_utils::{staking, SandboxEnvironment};
+use test_utils::{staking, utils::*, SandboxEnvironment};
 
 #[tokio::test]
 async fn update() -> anyhow::Result<()> {
@@ -7,6 +7,12 @@ async fn update() -> anyhow::Result<()> {
 
     let amount = staking::ft_total_supply(&contract).await?;
     assert_eq!(amount, 0_u128);
+    assert_eq!(get_state_version(&contract).await?, 1);
+
+    // Update of the same version keeps the state
+    contract.call("update").transact().await?.into_result()?;
+    assert_eq!(get_state_version(&contract).await?, 1);
+    assert_eq!(staking::ft_total_supply(&contract).await?, 0_u128);
 
     Ok(())
 }
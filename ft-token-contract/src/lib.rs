@@ -59,6 +59,7 @@ impl Contract {
             memo: None,
         }
         .emit();
+        common::state_version::set_state_version(update::STATE_VERSION);
 
         this
     }
@@ -1,4 +1,5 @@
 use crate::*;
+use common::events::{self, RealisEvent};
 use near_contract_standards::upgrade::Ownable;
 use near_sdk::assert_one_yocto;
 
@@ -10,6 +11,11 @@ impl Ownable for Contract {
 
     fn set_owner(&mut self, owner: AccountId) {
         self.assert_owner();
+        events::OwnerChange {
+            old_owner_id: &self.owner_id,
+            new_owner_id: &owner,
+        }
+        .emit();
         self.owner_id = owner;
     }
 }
@@ -21,6 +27,10 @@ impl Contract {
         assert_one_yocto();
         self.assert_owner();
 
+        events::BackendAdd {
+            backend_ids: &backend_ids,
+        }
+        .emit();
         self.backend.extend(backend_ids.into_iter());
     }
 
@@ -32,6 +42,10 @@ impl Contract {
         backend_ids.iter().for_each(|v| {
             self.backend.remove(v);
         });
+        events::BackendRemove {
+            backend_ids: &backend_ids,
+        }
+        .emit();
     }
 
     pub fn get_backend_accounts(&self) -> Vec<AccountId> {
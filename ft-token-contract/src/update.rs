@@ -1,10 +1,28 @@
 use crate::*;
+use common::{
+    impl_staged_upgrade,
+    state_version::{migration_start, read_state, set_state_version, state_version, StateVersion},
+};
+
+/// Version of the `Contract` layout
+pub const STATE_VERSION: StateVersion = 0;
 
 #[near_bindgen]
 impl Contract {
     #[private]
     #[init(ignore_state)]
     pub fn update() -> Self {
-        env::state_read().unwrap()
+        let contract = match migration_start(STATE_VERSION) {
+            0 => read_state::<Contract>(),
+            _ => env::panic_str("Unknown state version"),
+        };
+        set_state_version(STATE_VERSION);
+        contract
+    }
+
+    pub fn get_state_version(&self) -> StateVersion {
+        state_version()
     }
 }
+
+impl_staged_upgrade!(Contract);
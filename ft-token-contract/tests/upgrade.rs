@@ -0,0 +1,129 @@
+use near_sdk::serde_json;
+use test_utils::{token, utils::*, SandboxEnvironment};
+
+#[tokio::test]
+async fn stage_code() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let wasm = workspaces::compile_project("../ft-token-contract").await?;
+
+    // Only owner can stage the code
+    let result = sandbox
+        .backend
+        .call(sandbox.token.id(), "stage_code")
+        .args(wasm.clone())
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result();
+    assert!(result.is_err());
+
+    let staged: serde_json::Value = sandbox
+        .owner
+        .call(sandbox.token.id(), "stage_code")
+        .args(wasm)
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .json()?;
+
+    let view: serde_json::Value = sandbox
+        .token
+        .view(
+            "get_staged_code",
+            serde_json::json!({}).to_string().into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(staged, view);
+    assert_eq!(64, view["hash"].as_str().unwrap().len());
+
+    // The delay has not passed yet
+    let result = sandbox
+        .owner
+        .call(sandbox.token.id(), "deploy_staged_code")
+        .args_json(serde_json::json!({}))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result();
+    assert!(result.is_err());
+    let outcome = result.unwrap_err();
+    let failure = outcome.receipt_failures()[0];
+    assert!(format!("{:?}", failure).contains("The staged code can't be deployed yet"));
+
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "remove_staged_code")
+        .args_json(serde_json::json!({}))
+        .deposit(YOCTO)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let view: Option<serde_json::Value> = sandbox
+        .token
+        .view(
+            "get_staged_code",
+            serde_json::json!({}).to_string().into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert!(view.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn deploy_staged_code() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let sandbox = SandboxEnvironment::new(&worker).await?;
+    let wasm = workspaces::compile_project("../ft-token-contract").await?;
+
+    let staged: serde_json::Value = sandbox
+        .owner
+        .call(sandbox.token.id(), "stage_code")
+        .args(wasm)
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .json()?;
+    let deployable_at: u64 = staged["deployable_at"].as_str().unwrap().parse()?;
+
+    while worker.view_block().await?.timestamp() < deployable_at {
+        worker.fast_forward(10000).await?;
+    }
+
+    // The deploy fails as a whole if `update` fails,
+    // the staged code is removed by the callback after the successful deploy
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "deploy_staged_code")
+        .args_json(serde_json::json!({}))
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let view: Option<serde_json::Value> = sandbox
+        .token
+        .view(
+            "get_staged_code",
+            serde_json::json!({}).to_string().into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert!(view.is_none());
+    assert_eq!(0, get_state_version(&sandbox.token).await?);
+    assert_eq!(
+        3000000000000000000000,
+        token::ft_total_supply(&sandbox.token).await?
+    );
+
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use common::events::{self, RealisEvent};
 use near_contract_standards::non_fungible_token::{
     events::{NftBurn, NftMint, NftTransfer},
     metadata::TokenMetadata,
@@ -64,6 +65,7 @@ impl Contract {
         };
         this.measure_nft_storage_usage();
         this.mint_accounts.insert(&owner_id);
+        common::state_version::set_state_version(update::STATE_VERSION);
         this
     }
 
@@ -322,6 +324,10 @@ impl Contract {
             "Predecessor must be contract owner"
         );
 
+        events::MintAccountsAdd {
+            account_ids: &account_ids,
+        }
+        .emit();
         self.mint_accounts.extend(account_ids);
     }
 
@@ -342,6 +348,10 @@ impl Contract {
         account_ids.iter().for_each(|account_id| {
             self.mint_accounts.remove(account_id);
         });
+        events::MintAccountsRemove {
+            account_ids: &account_ids,
+        }
+        .emit();
     }
 }
 
@@ -1,6 +1,14 @@
 use crate::*;
+use common::{
+    events::{self, RealisEvent},
+    impl_staged_upgrade,
+    state_version::{migration_start, read_state, set_state_version, state_version, StateVersion},
+};
 use near_contract_standards::upgrade::Ownable;
 
+/// Version of the `Contract` layout
+pub const STATE_VERSION: StateVersion = 0;
+
 #[near_bindgen]
 impl Ownable for Contract {
     fn get_owner(&self) -> AccountId {
@@ -9,6 +17,11 @@ impl Ownable for Contract {
 
     fn set_owner(&mut self, owner: AccountId) {
         self.assert_owner();
+        events::OwnerChange {
+            old_owner_id: &self.owner_id,
+            new_owner_id: &owner,
+        }
+        .emit();
         self.owner_id = owner;
     }
 }
@@ -18,6 +31,17 @@ impl Contract {
     #[private]
     #[init(ignore_state)]
     pub fn update() -> Self {
-        env::state_read().unwrap_or_else(|| env::panic_str("Not initialized"))
+        let contract = match migration_start(STATE_VERSION) {
+            0 => read_state::<Contract>(),
+            _ => env::panic_str("Unknown state version"),
+        };
+        set_state_version(STATE_VERSION);
+        contract
+    }
+
+    pub fn get_state_version(&self) -> StateVersion {
+        state_version()
     }
 }
+
+impl_staged_upgrade!(Contract);
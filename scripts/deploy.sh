@@ -117,9 +117,15 @@ else
     echo "Updating contract"
     echo y | near deploy --accountId $LOCKUP_CONTRACT_ID \
         --wasmFile ./target/wasm32-unknown-unknown/release/ft_lockup_contract.wasm \
+        --initFunction "update" \
+        --initArgs '{}' \
         --initGas 300000000000000
 fi
 
+# Lockups created on unstake are paid from the storage balance of the staking contract
+echo "Top up storage balance of staking in lockup contract"
+near call $LOCKUP_CONTRACT_ID storage_deposit '{"account_id": "'$STAKING_CONTRACT_ID'"}' --accountId $OWNER_ID --deposit 10
+
 # Deploying staking contracts if not exists, otherwise update
 if [[ $(near view-state $STAKING_CONTRACT_ID --finality final) == *"[]"* ]];
 then
@@ -0,0 +1,15 @@
+use near_sdk::serde_json;
+use workspaces::{network::Sandbox, Contract, Worker};
+
+pub async fn new(worker: &Worker<Sandbox>) -> anyhow::Result<Contract> {
+    let wasm = workspaces::compile_project("../fake-lockup-contract").await?;
+    let contract = worker.dev_deploy(&wasm).await?;
+    contract
+        .call("new")
+        .args_json(serde_json::json!({}))
+        .transact()
+        .await?
+        .into_result()?;
+
+    Ok(contract)
+}
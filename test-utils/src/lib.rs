@@ -1,3 +1,4 @@
+pub mod fake_lockup;
 pub mod lockup;
 pub mod nft;
 pub mod staking;
@@ -19,6 +20,14 @@ pub struct SandboxEnvironment {
 
 impl SandboxEnvironment {
     pub async fn new(worker: &Worker<Sandbox>) -> anyhow::Result<Self> {
+        let mut sandbox = Self::with_legacy_lockup(worker).await?;
+        sandbox.upgrade_lockup().await?;
+        Ok(sandbox)
+    }
+
+    /// Environment with the lockup contract deployed from the mainnet,
+    /// `upgrade_lockup` deploys the current code.
+    pub async fn with_legacy_lockup(worker: &Worker<Sandbox>) -> anyhow::Result<Self> {
         let owner = worker.root_account()?;
         let backend = owner
             .create_subaccount("backend")
@@ -26,7 +35,7 @@ impl SandboxEnvironment {
             .transact()
             .await?
             .into_result()?;
-        
+
         let staking = staking::pull(
             worker,
             Some(owner.id().clone()),
@@ -41,7 +50,7 @@ impl SandboxEnvironment {
             STAKING_CONTRACT_ACCOUNT.parse()?,
         )
         .await?;
-        let lockup = lockup::pull(
+        let lockup = lockup::pull_legacy(
             worker,
             Some(owner.id().clone()),
             TOKEN_CONTRACT_ACCOUNT.parse()?,
@@ -89,4 +98,21 @@ impl SandboxEnvironment {
             nft,
         })
     }
+
+    pub async fn upgrade_lockup(&mut self) -> anyhow::Result<()> {
+        self.lockup = lockup::upgrade(&self.lockup).await?;
+
+        // Storage of the lockups created by the staking contract
+        self.owner
+            .call(self.lockup.id(), "storage_deposit")
+            .deposit(10 * NEAR)
+            .args_json(serde_json::json!({
+                "account_id": self.staking.id()
+            }))
+            .transact()
+            .await?
+            .into_result()?;
+
+        Ok(())
+    }
 }
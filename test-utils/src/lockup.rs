@@ -1,13 +1,25 @@
-use crate::utils::*;
+use crate::{utils::*, SandboxEnvironment};
 use near_sdk::{json_types::U64, serde_json};
 use near_units::parse_near;
-use workspaces::{network::Sandbox, AccountId, Contract, Worker};
+use std::collections::HashMap;
+use workspaces::{network::Sandbox, result::ExecutionSuccess, AccountId, Contract, Worker};
 
 pub async fn pull(
     worker: &Worker<Sandbox>,
     owner_id: Option<AccountId>,
     token_account_id: AccountId,
     deposit_whitelist: Vec<AccountId>,
+) -> anyhow::Result<Contract> {
+    let contract = pull_legacy(worker, owner_id, token_account_id, deposit_whitelist).await?;
+    upgrade(&contract).await
+}
+
+/// The contract deployed on the mainnet, with the state layout before the upgrade
+pub async fn pull_legacy(
+    worker: &Worker<Sandbox>,
+    owner_id: Option<AccountId>,
+    token_account_id: AccountId,
+    deposit_whitelist: Vec<AccountId>,
 ) -> anyhow::Result<Contract> {
     let mainnet = workspaces::mainnet_archival().await?;
     let contract_id: AccountId = LOCKUP_CONTRACT_ACCOUNT.parse()?;
@@ -19,11 +31,16 @@ pub async fn pull(
 
     contract
         .call("new")
-        .args_json(serde_json::json!({ "owner_od": owner_id, "token_account_id": token_account_id, "deposit_whitelist": deposit_whitelist }))
+        .args_json(serde_json::json!({ "owner_id": owner_id, "token_account_id": token_account_id, "deposit_whitelist": deposit_whitelist }))
         .transact()
         .await?
         .into_result()?;
 
+    Ok(contract)
+}
+
+/// Deploy the current code and migrate the state
+pub async fn upgrade(contract: &Contract) -> anyhow::Result<Contract> {
     let wasm = workspaces::compile_project("../ft-lockup-contract").await?;
     let contract = contract.as_account().deploy(&wasm).await?.result;
     contract.call("update").transact().await?.into_result()?;
@@ -42,3 +59,52 @@ pub async fn get_num_lockups(contract: &Contract) -> anyhow::Result<u64> {
 
     Ok(amount.0)
 }
+
+/// Create a lockup by the staking contract, the only account in the deposit whitelist.
+pub async fn create_lockup(
+    sandbox: &SandboxEnvironment,
+    amount: u128,
+    msg: serde_json::Value,
+) -> anyhow::Result<ExecutionSuccess> {
+    sandbox
+        .owner
+        .call(sandbox.token.id(), "ft_transfer")
+        .deposit(YOCTO)
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.staking.id(),
+            "amount": amount.to_string(),
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    Ok(sandbox
+        .staking
+        .as_account()
+        .call(sandbox.token.id(), "ft_transfer_call")
+        .deposit(YOCTO)
+        .gas(300000000000000)
+        .args_json(serde_json::json!({
+            "receiver_id": sandbox.lockup.id(),
+            "amount": amount.to_string(),
+            "msg": msg.to_string(),
+        }))
+        .transact()
+        .await?
+        .into_result()?)
+}
+
+pub async fn get_account_lockups(
+    contract: &Contract,
+    account_id: &AccountId,
+) -> anyhow::Result<HashMap<u32, serde_json::Value>> {
+    Ok(contract
+        .view(
+            "get_account_lockups",
+            serde_json::json!({ "account_id": account_id })
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?)
+}
@@ -3,6 +3,28 @@ use near_sdk::{json_types::U128, serde_json};
 use near_units::parse_near;
 use workspaces::{network::Sandbox, AccountId, Contract, Worker};
 
+pub async fn new(
+    worker: &Worker<Sandbox>,
+    owner_id: Option<AccountId>,
+    token_account_id: AccountId,
+    lockup_account_id: AccountId,
+) -> anyhow::Result<Contract> {
+    let wasm = workspaces::compile_project("../ft-staking-contract").await?;
+    let contract = worker.dev_deploy(&wasm).await?;
+    contract
+        .call("new")
+        .args_json(serde_json::json!({
+            "owner_id": owner_id,
+            "token_account_id": token_account_id,
+            "lockup_account_id": lockup_account_id,
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    Ok(contract)
+}
+
 pub async fn pull(
     worker: &Worker<Sandbox>,
     owner_id: Option<AccountId>,
@@ -16,3 +16,13 @@ pub const MINUTE: U64 = U64(60 * SECOND.0);
 pub const HOUR: U64 = U64(60 * MINUTE.0);
 pub const DAY: U64 = U64(24 * HOUR.0);
 pub const WEEK: U64 = U64(7 * DAY.0);
+
+pub async fn get_state_version(contract: &workspaces::Contract) -> anyhow::Result<u32> {
+    Ok(contract
+        .view(
+            "get_state_version",
+            near_sdk::serde_json::json!({}).to_string().into_bytes(),
+        )
+        .await?
+        .json()?)
+}